@@ -0,0 +1,332 @@
+use crate::config::{self, Options};
+use crate::detect::Tool;
+use crate::redact;
+use crate::rotate;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Pending requests and unused approvals are dropped after an hour
+const EXPIRY_SECS: i64 = 60 * 60;
+
+/// A confirm-rule command waiting for, or holding, a human's approval
+#[derive(Debug, Serialize, Deserialize)]
+struct Approval {
+    id: String,
+    /// Hash of tool, argv and repo; the argv itself isn't stored, so secrets
+    /// in it don't sit on disk
+    digest: String,
+    /// Redacted, for showing to the approver
+    command: String,
+    repo: String,
+    created: i64,
+    approved: bool,
+}
+
+impl Approval {
+    fn expired(&self, now: i64) -> bool {
+        now - self.created > EXPIRY_SECS
+    }
+}
+
+/// `~/.local/share/gg/approvals`, one JSON file per request
+fn default_dir() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("approvals"))
+}
+
+fn digest(tool: Tool, args: &[String], repo: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(tool.to_string());
+    for arg in args {
+        hasher.update([0]);
+        hasher.update(arg);
+    }
+    hasher.update([0, 0]);
+    hasher.update(repo);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Record a pending request and return its id. A retry before approval gets
+/// the id it was given the first time.
+pub fn request(tool: Tool, args: &[String], repo: &str, options: &Options) -> io::Result<String> {
+    let dir = default_dir().ok_or_else(|| io::Error::other("no home directory"))?;
+    request_in(&dir, tool, args, repo, options)
+}
+
+fn request_in(
+    dir: &Path,
+    tool: Tool,
+    args: &[String],
+    repo: &str,
+    options: &Options,
+) -> io::Result<String> {
+    let now = Utc::now().timestamp();
+    let digest = digest(tool, args, repo);
+    if let Some(existing) = load_all(dir, now)
+        .into_iter()
+        .find(|a| !a.approved && a.digest == digest)
+    {
+        return Ok(existing.id);
+    }
+
+    fs::create_dir_all(dir)?;
+    let id = digest_id(&digest, now);
+    let approval = Approval {
+        id: id.clone(),
+        digest,
+        command: format!(
+            "{} {}",
+            tool,
+            redact::args(args, &options.log_redact).join(" ")
+        ),
+        repo: repo.to_string(),
+        created: now,
+        approved: false,
+    };
+    save(dir, &approval)?;
+    Ok(id)
+}
+
+/// Six hex digits, unique enough among the handful of requests pending at once
+fn digest_id(digest: &str, now: i64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(digest);
+    hasher.update(now.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("{:x}", hasher.finalize())[..6].to_string()
+}
+
+/// Use up an approval for exactly this command in this repo, returning its id
+pub fn take(tool: Tool, args: &[String], repo: &str) -> Option<String> {
+    take_in(&default_dir()?, tool, args, repo)
+}
+
+fn take_in(dir: &Path, tool: Tool, args: &[String], repo: &str) -> Option<String> {
+    let digest = digest(tool, args, repo);
+    let approval = load_all(dir, Utc::now().timestamp())
+        .into_iter()
+        .find(|a| a.approved && a.digest == digest)?;
+    // Removing the file is what spends it: of two racing retries, one wins
+    fs::remove_file(path(dir, &approval.id))
+        .ok()
+        .map(|()| approval.id)
+}
+
+/// Every unexpired request, oldest first; expired ones are deleted on the way
+fn load_all(dir: &Path, now: i64) -> Vec<Approval> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut approvals: Vec<Approval> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                return None;
+            }
+            let approval: Approval = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            if approval.expired(now) {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+            Some(approval)
+        })
+        .collect();
+    approvals.sort_by_key(|a| a.created);
+    approvals
+}
+
+fn load(dir: &Path, id: &str) -> Option<Approval> {
+    // Ids are hex; anything else could walk out of the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let approval: Approval = serde_json::from_str(&fs::read_to_string(path(dir, id)).ok()?).ok()?;
+    (!approval.expired(Utc::now().timestamp())).then_some(approval)
+}
+
+fn path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+/// Written atomically so a reader never sees half a request
+fn save(dir: &Path, approval: &Approval) -> io::Result<()> {
+    let json = serde_json::to_string(approval).map_err(io::Error::other)?;
+    rotate::write_atomic(&path(dir, &approval.id), |file| {
+        restrict(file)?;
+        file.write_all(json.as_bytes())
+    })
+}
+
+#[cfg(unix)]
+fn restrict(file: &fs::File) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+/// `gg approve [<id>]`: list pending requests, or approve one from a terminal
+pub fn run(args: &[String]) -> ExitCode {
+    let Some(dir) = default_dir() else {
+        eprintln!("[gg] could not determine the approvals directory");
+        return ExitCode::FAILURE;
+    };
+    match args.first() {
+        None => list(&dir),
+        Some(id) => approve(&dir, id),
+    }
+}
+
+fn list(dir: &Path) -> ExitCode {
+    let now = Utc::now().timestamp();
+    let pending: Vec<Approval> = load_all(dir, now)
+        .into_iter()
+        .filter(|a| !a.approved)
+        .collect();
+    if pending.is_empty() {
+        println!("no pending requests");
+    }
+    for approval in pending {
+        println!(
+            "{}  {:>4}m ago  {}  in {}",
+            approval.id,
+            (now - approval.created) / 60,
+            approval.command,
+            approval.repo
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn approve(dir: &Path, id: &str) -> ExitCode {
+    // An agent has no terminal; requiring one keeps it from approving itself
+    if !io::stdin().is_terminal() {
+        eprintln!("[gg] gg approve must be run from a terminal");
+        return ExitCode::FAILURE;
+    }
+    let Some(mut approval) = load(dir, id) else {
+        eprintln!("[gg] no pending request `{}` (it may have expired)", id);
+        return ExitCode::FAILURE;
+    };
+    if approval.approved {
+        eprintln!("[gg] `{}` is already approved", id);
+        return ExitCode::SUCCESS;
+    }
+
+    eprintln!("[gg] request {}: `{}`", approval.id, approval.command);
+    eprintln!("[gg]   in {}", approval.repo);
+    eprint!("[gg] allow it to run once? [y/N] ");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    let yes = io::stdin().lock().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+
+    if !yes {
+        let _ = fs::remove_file(path(dir, &approval.id));
+        eprintln!("[gg] request {} rejected", approval.id);
+        return ExitCode::FAILURE;
+    }
+    approval.approved = true;
+    match save(dir, &approval) {
+        Ok(()) => {
+            eprintln!("[gg] approved; the next `{}` runs once", approval.command);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("[gg] could not save approval: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_approval_is_used_once_for_exact_command() {
+        let dir = temp_dir("gg_test_approval_once");
+        let push = args(&["push", "origin", "main"]);
+        let options = Options::default();
+
+        let id = request_in(&dir, Tool::Git, &push, "/src/app", &options).unwrap();
+        assert_eq!(id.len(), 6);
+        // Retrying before approval neither runs nor piles up requests
+        assert_eq!(take_in(&dir, Tool::Git, &push, "/src/app"), None);
+        assert_eq!(
+            request_in(&dir, Tool::Git, &push, "/src/app", &options).unwrap(),
+            id
+        );
+
+        let mut approval = load(&dir, &id).unwrap();
+        approval.approved = true;
+        save(&dir, &approval).unwrap();
+
+        assert_eq!(take_in(&dir, Tool::Git, &push, "/src/other"), None);
+        assert_eq!(
+            take_in(
+                &dir,
+                Tool::Git,
+                &args(&["push", "origin", "main", "--force"]),
+                "/src/app"
+            ),
+            None
+        );
+        assert_eq!(take_in(&dir, Tool::Gh, &push, "/src/app"), None);
+        assert_eq!(take_in(&dir, Tool::Git, &push, "/src/app"), Some(id));
+        assert_eq!(take_in(&dir, Tool::Git, &push, "/src/app"), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_expired_and_invalid_requests() {
+        let dir = temp_dir("gg_test_approval_expired");
+        fs::create_dir_all(&dir).unwrap();
+        let push = args(&["push"]);
+        let stale = Approval {
+            id: "abc123".to_string(),
+            digest: digest(Tool::Git, &push, "/src/app"),
+            command: "git push".to_string(),
+            repo: "/src/app".to_string(),
+            created: Utc::now().timestamp() - EXPIRY_SECS - 1,
+            approved: true,
+        };
+        save(&dir, &stale).unwrap();
+
+        assert_eq!(take_in(&dir, Tool::Git, &push, "/src/app"), None);
+        assert!(!path(&dir, "abc123").exists());
+        assert!(load(&dir, "../abc123").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_secrets_are_not_stored() {
+        let dir = temp_dir("gg_test_approval_redact");
+        let set = args(&["secret", "set", "FOO", "--body", "hunter2"]);
+        let id = request_in(&dir, Tool::Gh, &set, "/src/app", &Options::default()).unwrap();
+        let stored = fs::read_to_string(path(&dir, &id)).unwrap();
+        assert!(!stored.contains("hunter2"));
+        assert!(stored.contains("gh secret set FOO --body ***"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::detect::Tool;
+
+/// A git or gh command line split into global options, subcommand, options and operands.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandArgs {
+    pub globals: Vec<Opt>,
+    pub subcommand: Option<String>,
+    pub options: Vec<Opt>,
+    pub positionals: Vec<String>,
+    /// Operands after a `--` separator (pathspecs)
+    pub after_separator: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opt {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Opt {
+    fn flag(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: None,
+        }
+    }
+
+    fn with_value(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    fn is_long(&self) -> bool {
+        self.name.starts_with("--")
+    }
+}
+
+impl std::fmt::Display for Opt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            None => write!(f, "{}", self.name),
+            Some(v) if self.is_long() => write!(f, "{}={}", self.name, v),
+            Some(v) => write!(f, "{} {}", self.name, v),
+        }
+    }
+}
+
+/// Global options (before the subcommand) that take a separate value
+const GLOBAL_WITH_VALUE: &[&str] = &[
+    "-C",
+    "-c",
+    "--git-dir",
+    "--work-tree",
+    "--namespace",
+    "--super-prefix",
+    "--config-env",
+    "--attr-source",
+];
+
+/// Long options that take a value when written as `--opt value`
+const LONG_WITH_VALUE: &[&str] = &[
+    "--author",
+    "--branch",
+    "--cleanup",
+    "--date",
+    "--depth",
+    "--exec",
+    "--file",
+    "--jobs",
+    "--message",
+    "--onto",
+    "--origin",
+    "--push-option",
+    "--receive-pack",
+    "--reference",
+    "--repo",
+    "--reuse-message",
+    "--reedit-message",
+    "--separate-git-dir",
+    "--set-upstream-to",
+    "--strategy",
+    "--strategy-option",
+    "--template",
+    "--upload-pack",
+];
+
+/// Short options that take a value, per subcommand
+fn short_with_value(tool: Tool, subcommand: Option<&str>) -> &'static str {
+    if tool == Tool::Gh {
+        return match subcommand {
+            Some("api") => "RXHfFqt",
+            _ => "R",
+        };
+    }
+    match subcommand {
+        Some("commit") => "mFCct",
+        Some("push") => "o",
+        Some("clone") => "bocuj",
+        Some("checkout" | "switch") => "bBcC",
+        Some("branch") => "u",
+        Some("tag") => "mFu",
+        Some("log" | "shortlog") => "n",
+        Some("merge") => "mFsX",
+        Some("rebase") => "sXx",
+        Some("cherry-pick" | "revert") => "mXs",
+        Some("clean") => "e",
+        Some("stash") => "m",
+        Some("fetch" | "pull") => "jo",
+        Some("worktree") => "bB",
+        _ => "",
+    }
+}
+
+impl CommandArgs {
+    pub fn parse(tool: Tool, args: &[String]) -> Self {
+        let mut parsed = CommandArgs::default();
+        let mut iter = args.iter();
+
+        // gh has no options before its subcommand worth separating
+        if tool == Tool::Gh {
+            parsed.subcommand = iter.next().cloned();
+        }
+
+        while parsed.subcommand.is_none() {
+            let Some(arg) = iter.next() else { break };
+            if !arg.starts_with('-') || arg == "-" {
+                parsed.subcommand = Some(arg.clone());
+                break;
+            }
+            if let Some((name, value)) = arg.split_once('=').filter(|_| arg.starts_with("--")) {
+                parsed.globals.push(Opt::with_value(name, value));
+            } else if GLOBAL_WITH_VALUE.contains(&arg.as_str()) {
+                match iter.next() {
+                    Some(v) => parsed.globals.push(Opt::with_value(arg, v)),
+                    None => parsed.globals.push(Opt::flag(arg)),
+                }
+            } else {
+                parsed.globals.push(Opt::flag(arg));
+            }
+        }
+
+        let short_values = short_with_value(tool, parsed.subcommand.as_deref());
+
+        while let Some(arg) = iter.next() {
+            if let Some(rest) = parsed.after_separator.as_mut() {
+                rest.push(arg.clone());
+            } else if arg == "--" {
+                parsed.after_separator = Some(Vec::new());
+            } else if arg.starts_with("--") {
+                if let Some((name, value)) = arg.split_once('=') {
+                    parsed.options.push(Opt::with_value(name, value));
+                } else if LONG_WITH_VALUE.contains(&arg.as_str()) {
+                    match iter.next() {
+                        Some(v) => parsed.options.push(Opt::with_value(arg, v)),
+                        None => parsed.options.push(Opt::flag(arg)),
+                    }
+                } else {
+                    parsed.options.push(Opt::flag(arg));
+                }
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                parse_short_cluster(&arg[1..], short_values, &mut iter, &mut parsed.options);
+            } else {
+                parsed.positionals.push(arg.clone());
+            }
+        }
+
+        parsed
+    }
+
+    /// Tokens in canonical order: subcommand, options, operands, `--` and pathspecs.
+    /// Global options are dropped, so deny and confirm rules see through them;
+    /// `-c` and `-C` can change what the command does, so allow rules must not.
+    pub fn normalized(&self) -> Vec<String> {
+        self.tokens(self.options.iter())
+    }
+
+    /// One normalized command string per option, with that option moved to the front.
+    /// Lets a pattern like `push --force*` match regardless of where `--force` appeared.
+    pub fn normalized_forms(&self) -> Vec<String> {
+        let mut forms = vec![self.normalized().join(" ")];
+        for (i, lead) in self.options.iter().enumerate() {
+            let rest = self
+                .options
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, o)| o);
+            let form = self.tokens(std::iter::once(lead).chain(rest)).join(" ");
+            if !forms.contains(&form) {
+                forms.push(form);
+            }
+        }
+        forms
+    }
+
+    fn tokens<'a>(&self, options: impl Iterator<Item = &'a Opt>) -> Vec<String> {
+        let mut tokens: Vec<String> = self.subcommand.iter().cloned().collect();
+        tokens.extend(options.map(|o| o.to_string()));
+        tokens.extend(self.positionals.iter().cloned());
+        if let Some(rest) = &self.after_separator {
+            tokens.push("--".to_string());
+            tokens.extend(rest.iter().cloned());
+        }
+        tokens
+    }
+}
+
+/// Expand `-fu` into `-f -u`; a value-taking letter consumes the rest of the
+/// cluster (`-mfix`) or the next argument (`-am fix`).
+fn parse_short_cluster<'a>(
+    cluster: &str,
+    short_values: &str,
+    iter: &mut impl Iterator<Item = &'a String>,
+    options: &mut Vec<Opt>,
+) {
+    for (i, c) in cluster.char_indices() {
+        let name = format!("-{}", c);
+        if !short_values.contains(c) {
+            options.push(Opt::flag(name));
+            continue;
+        }
+        let attached = &cluster[i + c.len_utf8()..];
+        if !attached.is_empty() {
+            options.push(Opt::with_value(name, attached));
+        } else {
+            match iter.next() {
+                Some(v) => options.push(Opt::with_value(name, v)),
+                None => options.push(Opt::flag(name)),
+            }
+        }
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn parse(s: &str) -> CommandArgs {
+        CommandArgs::parse(Tool::Git, &args(s))
+    }
+
+    #[test]
+    fn test_plain_command() {
+        let parsed = parse("push origin main");
+        assert_eq!(parsed.subcommand.as_deref(), Some("push"));
+        assert!(parsed.options.is_empty());
+        assert_eq!(parsed.positionals, vec!["origin", "main"]);
+    }
+
+    #[test]
+    fn test_global_options_are_separated() {
+        let parsed = parse("-c foo=bar -C /tmp --git-dir=.git --no-pager push -f");
+        assert_eq!(parsed.globals.len(), 4);
+        assert_eq!(parsed.globals[0], Opt::with_value("-c", "foo=bar"));
+        assert_eq!(parsed.globals[1], Opt::with_value("-C", "/tmp"));
+        assert_eq!(parsed.globals[2], Opt::with_value("--git-dir", ".git"));
+        assert_eq!(parsed.subcommand.as_deref(), Some("push"));
+        assert_eq!(parsed.normalized().join(" "), "push -f");
+
+        let parsed = parse("--attr-source HEAD~1 push --force");
+        assert_eq!(
+            parsed.globals,
+            vec![Opt::with_value("--attr-source", "HEAD~1")]
+        );
+        assert_eq!(parsed.subcommand.as_deref(), Some("push"));
+        assert_eq!(parsed.normalized().join(" "), "push --force");
+    }
+
+    #[test]
+    fn test_combined_short_flags_are_expanded() {
+        let parsed = parse("push -fu origin");
+        assert_eq!(parsed.options, vec![Opt::flag("-f"), Opt::flag("-u")]);
+        assert_eq!(parsed.normalized().join(" "), "push -f -u origin");
+    }
+
+    #[test]
+    fn test_short_value_in_cluster() {
+        let parsed = parse("commit -am fix");
+        assert_eq!(
+            parsed.options,
+            vec![Opt::flag("-a"), Opt::with_value("-m", "fix")]
+        );
+        assert!(parsed.positionals.is_empty());
+
+        let parsed = parse("commit -mfix");
+        assert_eq!(parsed.options, vec![Opt::with_value("-m", "fix")]);
+    }
+
+    #[test]
+    fn test_long_option_value_forms() {
+        let a = parse("push --repo=origin main");
+        let b = parse("push --repo origin main");
+        assert_eq!(a, b);
+        assert_eq!(a.positionals, vec!["main"]);
+        assert_eq!(a.normalized().join(" "), "push --repo=origin main");
+    }
+
+    #[test]
+    fn test_separator() {
+        let parsed = parse("checkout main -- -f .");
+        assert_eq!(parsed.positionals, vec!["main"]);
+        assert!(parsed.options.is_empty());
+        assert_eq!(
+            parsed.after_separator,
+            Some(vec!["-f".to_string(), ".".to_string()])
+        );
+        assert_eq!(parsed.normalized().join(" "), "checkout main -- -f .");
+    }
+
+    #[test]
+    fn test_options_move_before_operands() {
+        let parsed = parse("push origin main --force");
+        assert_eq!(parsed.normalized().join(" "), "push --force origin main");
+    }
+
+    #[test]
+    fn test_normalized_forms_rotate_each_option() {
+        let forms = parse("push -u origin --force").normalized_forms();
+        assert_eq!(forms[0], "push -u --force origin");
+        assert!(forms.contains(&"push --force -u origin".to_string()));
+    }
+
+    #[test]
+    fn test_gh_args() {
+        let parsed = CommandArgs::parse(Tool::Gh, &args("api -X DELETE /repos/o/r -R o/r"));
+        assert_eq!(parsed.subcommand.as_deref(), Some("api"));
+        assert_eq!(
+            parsed.options,
+            vec![
+                Opt::with_value("-X", "DELETE"),
+                Opt::with_value("-R", "o/r")
+            ]
+        );
+        assert_eq!(parsed.positionals, vec!["/repos/o/r"]);
+    }
+
+    #[test]
+    fn test_no_subcommand() {
+        let parsed = parse("--version");
+        assert!(parsed.subcommand.is_none());
+        assert!(parsed.normalized().is_empty());
+    }
+}
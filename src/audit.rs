@@ -0,0 +1,510 @@
+use crate::chain::{self, Head};
+use crate::config::Config;
+use crate::logger::{self, Event};
+use crate::rotate;
+use crate::rules;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+/// `gg audit` subcommands. They aren't under `gg log`, where a branch named
+/// `show` would turn `git log show` into one of them.
+const SUBCOMMANDS: &[&str] = &["verify", "show", "top", "summary"];
+
+/// What `--decision blocked` expands to, and what `top` counts by default
+const BLOCKED: [&str; 2] = ["deny", "default_deny"];
+
+const USAGE: &str = "usage: gg audit verify
+       gg audit show    [filters] [--limit N]
+       gg audit top     [filters] [--limit N]   (blocked commands unless --decision is given)
+       gg audit summary [filters]
+
+filters:
+  --decision LIST   allow, confirm, deny, default_deny or blocked (comma-separated)
+  --tool git|gh
+  --since WHEN      YYYY-MM-DD, \"YYYY-MM-DD HH:MM\" or an age like 12h, 7d
+  --until WHEN
+  --repo TEXT       repo root contains TEXT (JSON logs only)
+  --pattern PAT     command matches a rule-style pattern, e.g. \"push*\"";
+
+/// `gg audit <subcommand> [args...]`
+pub fn run(config: &Config, args: &[String]) -> ExitCode {
+    let sub = args.first().map(String::as_str);
+    if !sub.is_some_and(|sub| SUBCOMMANDS.contains(&sub)) {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+    if sub == Some("verify") {
+        return verify(config);
+    }
+    let mut query = match Query::parse(&args[1..]) {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("[gg] {}", e);
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if sub == Some("top") && query.decisions.is_empty() {
+        query.decisions = BLOCKED.map(String::from).to_vec();
+    }
+    let Some(path) = logger::log_path(&config.options) else {
+        eprintln!("[gg] could not determine log path");
+        return ExitCode::FAILURE;
+    };
+    let content = match rotate::read_all(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("[gg] cannot read {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let entries: Vec<Entry> = content
+        .lines()
+        .filter_map(Entry::parse)
+        .filter(|e| query.matches(e))
+        .collect();
+
+    let output = match sub {
+        Some("show") => show(&entries, query.limit),
+        Some("top") => top(&entries, query.limit.unwrap_or(10)),
+        Some("summary") => summary(&entries),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    print!("{}", output);
+    ExitCode::SUCCESS
+}
+
+/// A parsed audit log line, text or JSON
+#[derive(Debug)]
+struct Entry<'a> {
+    line: &'a str,
+    time: NaiveDateTime,
+    event: Event,
+    /// Lowercase, e.g. `default_deny`; text result lines have none
+    decision: Option<String>,
+    tool: String,
+    /// Arguments after the tool, space-joined
+    command: String,
+    repo: Option<String>,
+}
+
+impl<'a> Entry<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        if line.starts_with('{') {
+            Self::parse_json(line)
+        } else {
+            Self::parse_text(line)
+        }
+    }
+
+    /// `[ts] DECISION | tool cmd | ...` or `[ts] RESULT | tool cmd | ...`
+    fn parse_text(line: &'a str) -> Option<Self> {
+        let (ts, rest) = line.strip_prefix('[')?.split_once("] ")?;
+        let time = NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S").ok()?;
+        let (word, rest) = rest.split_once(" | ")?;
+        let command = rest.split(" | ").next()?;
+        let (tool, command) = command.split_once(' ').unwrap_or((command, ""));
+        let (event, decision) = match word {
+            "RESULT" => (Event::Result, None),
+            "GRANT" => (Event::Grant, None),
+            "REVOKE" => (Event::Revoke, None),
+            _ => (Event::Decision, Some(word.to_lowercase())),
+        };
+        Some(Self {
+            line,
+            time,
+            event,
+            decision,
+            tool: tool.to_string(),
+            command: command.to_string(),
+            repo: None,
+        })
+    }
+
+    fn parse_json(line: &'a str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let time = DateTime::parse_from_rfc3339(value["ts"].as_str()?).ok()?;
+        let event = match value["event"].as_str() {
+            Some("result") => Event::Result,
+            Some("grant") => Event::Grant,
+            Some("revoke") => Event::Revoke,
+            _ => Event::Decision,
+        };
+        let argv: Vec<&str> = value["argv"]
+            .as_array()?
+            .iter()
+            .filter_map(|a| a.as_str())
+            .collect();
+        Some(Self {
+            line,
+            time: time.with_timezone(&Local).naive_local(),
+            event,
+            decision: (event == Event::Decision)
+                .then(|| value["decision"].as_str().map(str::to_string))
+                .flatten(),
+            tool: value["tool"].as_str()?.to_string(),
+            command: argv.join(" "),
+            repo: value["repo_root"].as_str().map(str::to_string),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Query {
+    decisions: Vec<String>,
+    tool: Option<String>,
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    repo: Option<String>,
+    pattern: Option<String>,
+    limit: Option<usize>,
+}
+
+impl Query {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        Self::parse_at(args, Local::now().naive_local())
+    }
+
+    fn parse_at(args: &[String], now: NaiveDateTime) -> Result<Self, String> {
+        let mut query = Query::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match flag.as_str() {
+                "--decision" => {
+                    for d in value()?.split(',') {
+                        match d {
+                            "blocked" => query.decisions.extend(BLOCKED.map(String::from)),
+                            "allow" | "confirm" | "deny" | "default_deny" => {
+                                query.decisions.push(d.to_string())
+                            }
+                            _ => return Err(format!("unknown decision `{}`", d)),
+                        }
+                    }
+                }
+                "--tool" => query.tool = Some(value()?),
+                "--since" => query.since = Some(parse_when(&value()?, now, false)?),
+                "--until" => query.until = Some(parse_when(&value()?, now, true)?),
+                "--repo" => query.repo = Some(value()?),
+                "--pattern" => query.pattern = Some(value()?),
+                "--limit" => {
+                    let n = value()?;
+                    query.limit = Some(n.parse().map_err(|_| format!("bad --limit `{}`", n))?);
+                }
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        Ok(query)
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        // Result lines repeat the decision in JSON logs only, so a decision
+        // filter keeps decision lines alone to treat both formats the same
+        let decided = entry.event == Event::Decision
+            && entry
+                .decision
+                .as_ref()
+                .is_some_and(|d| self.decisions.contains(d));
+        if !self.decisions.is_empty() && !decided {
+            return false;
+        }
+        if self.tool.as_ref().is_some_and(|t| *t != entry.tool) {
+            return false;
+        }
+        if self.since.is_some_and(|t| entry.time < t) || self.until.is_some_and(|t| entry.time >= t)
+        {
+            return false;
+        }
+        if let Some(repo) = &self.repo
+            && !entry
+                .repo
+                .as_ref()
+                .is_some_and(|r| r.contains(repo.as_str()))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.pattern
+            && !rules::matches_pattern(pattern, &entry.command)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// `2026-03-01`, `2026-03-01 12:30` or an age like `30m`, `12h`, `7d`.
+/// A bare date used as an end bound covers that whole day.
+fn parse_when(text: &str, now: NaiveDateTime, end: bool) -> Result<NaiveDateTime, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        let date = if end {
+            date.succ_opt().unwrap_or(date)
+        } else {
+            date
+        };
+        return Ok(date.and_hms_opt(0, 0, 0).expect("midnight exists"));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+        return Ok(time);
+    }
+    parse_age(text)
+        .and_then(|age| now.checked_sub_signed(age))
+        .ok_or_else(|| format!("cannot parse time `{}`", text))
+}
+
+/// `30m`, `12h` or `7d`; `None` too for ages beyond what a duration holds
+pub fn parse_age(text: &str) -> Option<Duration> {
+    let (amount, unit) = text.split_at(text.len().saturating_sub(1));
+    let amount: i64 = amount.parse().ok().filter(|n| *n >= 0)?;
+    match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => None,
+    }
+}
+
+/// Matching lines as written, the last `limit` if given
+fn show(entries: &[Entry], limit: Option<usize>) -> String {
+    let skip = limit.map_or(0, |n| entries.len().saturating_sub(n));
+    entries[skip..]
+        .iter()
+        .map(|e| format!("{}\n", e.line))
+        .collect()
+}
+
+/// Most frequent commands among decision entries; blocked ones by default
+fn top(entries: &[Entry], limit: usize) -> String {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.event == Event::Decision) {
+        *counts
+            .entry(format!("{} {}", entry.tool, entry.command))
+            .or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .take(limit)
+        .map(|(command, n)| format!("{:>6}  {}\n", n, command))
+        .collect()
+}
+
+/// Decision counts per day
+fn summary(entries: &[Entry]) -> String {
+    const COLUMNS: [&str; 4] = ["allow", "confirm", "deny", "default_deny"];
+    let mut days: BTreeMap<NaiveDate, [usize; 4]> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.event == Event::Decision) {
+        let Some(column) = entry
+            .decision
+            .as_deref()
+            .and_then(|d| COLUMNS.iter().position(|c| *c == d))
+        else {
+            continue;
+        };
+        days.entry(entry.time.date()).or_default()[column] += 1;
+    }
+    let mut out = format!(
+        "{:<10}  {:>6}  {:>7}  {:>6}  {:>12}\n",
+        "date", COLUMNS[0], COLUMNS[1], COLUMNS[2], COLUMNS[3]
+    );
+    for (day, c) in days {
+        out.push_str(&format!(
+            "{:<10}  {:>6}  {:>7}  {:>6}  {:>12}\n",
+            day.to_string(),
+            c[0],
+            c[1],
+            c[2],
+            c[3]
+        ));
+    }
+    out
+}
+
+/// `gg audit verify`: check the hash chain of the audit log
+fn verify(config: &Config) -> ExitCode {
+    let Some(path) = logger::log_path(&config.options) else {
+        eprintln!("[gg] could not determine log path");
+        return ExitCode::FAILURE;
+    };
+    let content = match rotate::read_all(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("[gg] cannot read {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let key = logger::hmac_key(&config.options);
+    if config.options.log_hmac_key_file.is_some() && key.is_none() {
+        return ExitCode::FAILURE;
+    }
+    let base = Head::read_base_checked(&path, key.as_deref());
+    let head = Head::read_checked(&path, key.as_deref());
+
+    // Without the key, .head and .base could be rewritten to fit a log cut
+    // down at either end
+    let mut problems: Vec<String> = [(".base", &base), (".head", &head)]
+        .into_iter()
+        .filter(|(_, stored)| stored.as_ref().is_some_and(|(_, authentic)| !authentic))
+        .map(|(name, _)| format!("{} was not written with log_hmac_key_file", name))
+        .collect();
+    let (base, head) = (base.map(|(b, _)| b), head.map(|(h, _)| h));
+    problems.extend(chain::verify(
+        &content,
+        base.as_ref(),
+        head.as_ref(),
+        key.as_deref(),
+    ));
+    for problem in &problems {
+        eprintln!("[gg] {}: {}", path.display(), problem);
+    }
+    match (&head, problems.is_empty()) {
+        (_, false) => {
+            eprintln!("[gg] audit verify: {} problem(s)", problems.len());
+            ExitCode::FAILURE
+        }
+        (None, true) => {
+            eprintln!(
+                "[gg] audit verify: no chained records in {} (is log_chain enabled?)",
+                path.display()
+            );
+            ExitCode::FAILURE
+        }
+        (Some(head), true) => {
+            eprintln!("[gg] audit verify: ok, {} records", head.seq);
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"[2026-03-01 09:00:00] ALLOW | git status
+[2026-03-01 09:00:00] RESULT | git status | exit 0 | 3ms
+[2026-03-01 10:00:00] DENY | git push --force origin main | rule deny[0] "push --force*"
+[2026-03-02 08:30:00] DEFAULT_DENY | gh repo delete foo | no matching rule | seq 4 | chain ab
+[2026-03-02 08:31:00] DENY | git push --force origin main | rule deny[0] "push --force*"
+[2026-03-02 09:00:00] CONFIRM | git reset --hard | rule confirm[0] "reset --hard*"
+not a log line
+"#;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-03-02 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn query(list: &[&str]) -> Query {
+        Query::parse_at(&args(list), now()).unwrap()
+    }
+
+    fn select<'a>(content: &'a str, query: &Query) -> Vec<Entry<'a>> {
+        content
+            .lines()
+            .filter_map(Entry::parse)
+            .filter(|e| query.matches(e))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_text_lines() {
+        let entries = select(LOG, &Query::default());
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[1].event, Event::Result);
+        assert_eq!(entries[1].decision, None);
+        assert_eq!(entries[2].decision.as_deref(), Some("deny"));
+        assert_eq!(entries[2].command, "push --force origin main");
+        assert_eq!(entries[3].tool, "gh");
+        assert_eq!(entries[3].command, "repo delete foo");
+        assert_eq!(entries[3].decision.as_deref(), Some("default_deny"));
+    }
+
+    #[test]
+    fn test_parse_json_line() {
+        let line = r#"{"argv":["push","--force"],"decision":"deny","event":"decision","repo_root":"/src/app","tool":"git","ts":"2026-03-01T10:00:00.000+00:00"}"#;
+        let entry = Entry::parse(line).unwrap();
+        assert_eq!(entry.tool, "git");
+        assert_eq!(entry.command, "push --force");
+        assert_eq!(entry.decision.as_deref(), Some("deny"));
+        assert_eq!(entry.repo.as_deref(), Some("/src/app"));
+
+        assert!(query(&["--repo", "app"]).matches(&entry));
+        assert!(!query(&["--repo", "other"]).matches(&entry));
+    }
+
+    #[test]
+    fn test_filters() {
+        let blocked = select(LOG, &query(&["--decision", "blocked"]));
+        assert_eq!(blocked.len(), 3);
+
+        let gh = select(LOG, &query(&["--tool", "gh"]));
+        assert_eq!(gh.len(), 1);
+
+        let day = select(
+            LOG,
+            &query(&["--since", "2026-03-01", "--until", "2026-03-01"]),
+        );
+        assert_eq!(day.len(), 3);
+
+        let recent = select(LOG, &query(&["--since", "4h"]));
+        assert_eq!(recent.len(), 3);
+
+        let pushes = select(LOG, &query(&["--pattern", "push --force*"]));
+        assert_eq!(pushes.len(), 2);
+
+        // Text logs have no repo, so a repo filter drops every line
+        assert!(select(LOG, &query(&["--repo", "app"])).is_empty());
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        assert!(Query::parse_at(&args(&["--decision", "maybe"]), now()).is_err());
+        assert!(Query::parse_at(&args(&["--since", "yesterday"]), now()).is_err());
+        assert!(Query::parse_at(&args(&["--since", "99999999999999999d"]), now()).is_err());
+        assert!(Query::parse_at(&args(&["--until", "9999999999999h"]), now()).is_err());
+        assert!(Query::parse_at(&args(&["--limit"]), now()).is_err());
+        assert!(Query::parse_at(&args(&["--verbose"]), now()).is_err());
+    }
+
+    #[test]
+    fn test_top_and_summary() {
+        let blocked = select(LOG, &query(&["--decision", "blocked"]));
+        assert_eq!(
+            top(&blocked, 10),
+            "     2  git push --force origin main\n     1  gh repo delete foo\n"
+        );
+        assert_eq!(top(&blocked, 1), "     2  git push --force origin main\n");
+
+        let all = select(LOG, &Query::default());
+        let summary = summary(&all);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<_>>(),
+            ["2026-03-01", "1", "0", "1", "0"]
+        );
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<_>>(),
+            ["2026-03-02", "0", "1", "1", "1"]
+        );
+    }
+
+    #[test]
+    fn test_show_limit_keeps_latest() {
+        let all = select(LOG, &Query::default());
+        let shown = show(&all, Some(1));
+        assert!(shown.starts_with("[2026-03-02 09:00:00] CONFIRM"));
+        assert_eq!(shown.lines().count(), 1);
+    }
+}
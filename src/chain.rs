@@ -0,0 +1,398 @@
+use crate::rotate;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// `prev` for the first record of a chain
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const TEXT_SEQ: &str = " | seq ";
+const TEXT_CHAIN: &str = " | chain ";
+
+/// Last sealed record, kept next to the log as `<log>.head` so truncation of
+/// the log itself can be detected. With an HMAC key the file also carries a
+/// MAC over `seq chain`, so it can't be rewritten to fit a cut-down log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub seq: u64,
+    pub chain: String,
+}
+
+impl Head {
+    pub fn genesis() -> Head {
+        Head {
+            seq: 0,
+            chain: GENESIS.to_string(),
+        }
+    }
+
+    pub fn path(log: &Path) -> PathBuf {
+        sibling(log, ".head")
+    }
+
+    /// `<log>.base`: the last record of the newest rotated file that was
+    /// deleted, which the oldest remaining record chains from
+    pub fn base_path(log: &Path) -> PathBuf {
+        sibling(log, ".base")
+    }
+
+    pub fn read(log: &Path) -> Option<Head> {
+        Self::read_from(&Self::path(log)).map(|(head, _)| head)
+    }
+
+    pub fn write(&self, log: &Path, key: Option<&[u8]>) -> std::io::Result<()> {
+        self.write_to(&Self::path(log), "head", key)
+    }
+
+    pub fn write_base(&self, log: &Path, key: Option<&[u8]>) -> std::io::Result<()> {
+        self.write_to(&Self::base_path(log), "base", key)
+    }
+
+    /// `.head` and whether its MAC checks out against `key`; always true
+    /// without a key
+    pub fn read_checked(log: &Path, key: Option<&[u8]>) -> Option<(Head, bool)> {
+        Self::read_checked_from(&Self::path(log), "head", key)
+    }
+
+    /// `.base` and whether its MAC checks out against `key`
+    pub fn read_base_checked(log: &Path, key: Option<&[u8]>) -> Option<(Head, bool)> {
+        Self::read_checked_from(&Self::base_path(log), "base", key)
+    }
+
+    fn read_checked_from(path: &Path, role: &str, key: Option<&[u8]>) -> Option<(Head, bool)> {
+        let (head, mac) = Self::read_from(path)?;
+        let authentic = key.is_none_or(|key| mac.as_deref() == Some(&head.mac(role, key)));
+        Some((head, authentic))
+    }
+
+    fn read_from(path: &Path) -> Option<(Head, Option<String>)> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut fields = content.split_whitespace();
+        let head = Head {
+            seq: fields.next()?.parse().ok()?,
+            chain: fields.next()?.to_string(),
+        };
+        Some((head, fields.next().map(String::from)))
+    }
+
+    /// Replaced by rename, so a crash never leaves a half-written head
+    fn write_to(&self, path: &Path, role: &str, key: Option<&[u8]>) -> std::io::Result<()> {
+        rotate::write_atomic(path, |file| match key {
+            Some(key) => writeln!(file, "{} {} {}", self.seq, self.chain, self.mac(role, key)),
+            None => writeln!(file, "{} {}", self.seq, self.chain),
+        })
+    }
+
+    /// Keyed with the file's role, so a `.base` can't stand in for a `.head`
+    fn mac(&self, role: &str, key: &[u8]) -> String {
+        link(role, &format!("{} {}", self.seq, self.chain), Some(key))
+    }
+}
+
+fn sibling(log: &Path, suffix: &str) -> PathBuf {
+    let mut name = log.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Hash of a record body chained to the previous record's hash, keyed with
+/// HMAC-SHA256 when a key is configured
+pub fn link(prev: &str, body: &str, key: Option<&[u8]>) -> String {
+    let bytes = match key {
+        Some(key) => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key length");
+            mac.update(prev.as_bytes());
+            mac.update(b"\n");
+            mac.update(body.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        None => {
+            let mut hasher = Sha256::new();
+            hasher.update(prev.as_bytes());
+            hasher.update(b"\n");
+            hasher.update(body.as_bytes());
+            hasher.finalize().to_vec()
+        }
+    };
+    bytes.iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+/// Append `seq` and `chain` to a log line (without its trailing newline).
+/// Returns the sealed line and its head.
+pub fn seal(line: &str, prev: &Head, key: Option<&[u8]>) -> (String, Head) {
+    let seq = prev.seq + 1;
+    match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert("seq".into(), seq.into());
+            let body = serde_json::Value::Object(map.clone()).to_string();
+            let chain = link(&prev.chain, &body, key);
+            map.insert("chain".into(), chain.clone().into());
+            (
+                serde_json::Value::Object(map).to_string(),
+                Head { seq, chain },
+            )
+        }
+        _ => {
+            let body = format!("{}{}{}", line, TEXT_SEQ, seq);
+            let chain = link(&prev.chain, &body, key);
+            let sealed = format!("{}{}{}", body, TEXT_CHAIN, chain);
+            (sealed, Head { seq, chain })
+        }
+    }
+}
+
+/// Split a sealed line into the hashed body, its sequence number and chain
+/// value. `None` if the line was written without chaining.
+fn unseal(line: &str) -> Option<(String, u64, String)> {
+    if line.starts_with('{') {
+        let serde_json::Value::Object(mut map) = serde_json::from_str(line).ok()? else {
+            return None;
+        };
+        let chain = map.remove("chain")?.as_str()?.to_string();
+        let seq = map.get("seq")?.as_u64()?;
+        return Some((serde_json::Value::Object(map).to_string(), seq, chain));
+    }
+    let (body, chain) = line.rsplit_once(TEXT_CHAIN)?;
+    let (_, seq) = body.rsplit_once(TEXT_SEQ)?;
+    Some((body.to_string(), seq.parse().ok()?, chain.to_string()))
+}
+
+/// The last sealed record in `content`
+pub fn last_head(content: &str) -> Option<Head> {
+    let (_, seq, chain) = content.lines().rev().find_map(unseal)?;
+    Some(Head { seq, chain })
+}
+
+/// Check every sealed line of `content` against the chain, starting from
+/// `base` (or the genesis value), and the last one against `head`. Returns
+/// one message per problem.
+pub fn verify(
+    content: &str,
+    base: Option<&Head>,
+    head: Option<&Head>,
+    key: Option<&[u8]>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut prev: Option<Head> = base.cloned();
+
+    for (number, line) in content.lines().enumerate() {
+        let number = number + 1;
+        let Some((body, seq, chain)) = unseal(line) else {
+            // Lines written before chaining was turned on
+            if prev.as_ref().is_some_and(|p| p.seq > 0) {
+                problems.push(format!("line {}: record has no chain value", number));
+            }
+            continue;
+        };
+        let expected = prev.as_ref().map(|p| p.seq + 1).unwrap_or(1);
+        if seq != expected {
+            problems.push(format!(
+                "line {}: seq {} where {} was expected (records removed or reordered)",
+                number, seq, expected
+            ));
+        }
+        let prev_chain = prev.as_ref().map(|p| p.chain.as_str()).unwrap_or(GENESIS);
+        if link(prev_chain, &body, key) != chain {
+            problems.push(format!(
+                "line {}: chain mismatch (record edited, or the one before it changed)",
+                number
+            ));
+        }
+        prev = Some(Head { seq, chain });
+    }
+
+    match (head, &prev) {
+        (Some(head), Some(last)) if head != last => {
+            if head.seq > last.seq {
+                problems.push(format!(
+                    "log ends at seq {} but .head says {} (log truncated)",
+                    last.seq, head.seq
+                ));
+            } else {
+                problems.push(format!(
+                    "last record (seq {}) does not match .head (seq {})",
+                    last.seq, head.seq
+                ));
+            }
+        }
+        (Some(head), None) => problems.push(format!(
+            "no chained records, but .head says seq {} (log truncated)",
+            head.seq
+        )),
+        (None, Some(last)) => problems.push(format!(
+            ".head is missing (last record is seq {})",
+            last.seq
+        )),
+        _ => {}
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seal `lines` in order, returning the log content and final head
+    fn build(lines: &[&str], key: Option<&[u8]>) -> (String, Head) {
+        let mut head = Head::genesis();
+        let mut content = String::new();
+        for line in lines {
+            let (sealed, next) = seal(line, &head, key);
+            content.push_str(&sealed);
+            content.push('\n');
+            head = next;
+        }
+        (content, head)
+    }
+
+    const LINES: [&str; 3] = [
+        "[2026-01-01 00:00:00] ALLOW | git status",
+        r#"{"argv":["push"],"decision":"deny","tool":"git"}"#,
+        "[2026-01-01 00:00:02] DENY | git push --force",
+    ];
+
+    #[test]
+    fn test_intact_chain_verifies() {
+        let (content, head) = build(&LINES, None);
+        assert_eq!(head.seq, 3);
+        assert!(content.lines().nth(1).unwrap().contains(r#""seq":2"#));
+        assert_eq!(
+            verify(&content, None, Some(&head), None),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_detects_edit() {
+        let (content, head) = build(&LINES, None);
+        let edited = content.replace("DENY | git push --force", "ALLOW | git push --force");
+        let problems = verify(&edited, None, Some(&head), None);
+        assert!(
+            problems[0].starts_with("line 3: chain mismatch"),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn test_detects_reorder_and_removal() {
+        let (content, head) = build(&LINES, None);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let reordered = format!("{}\n{}\n{}\n", lines[1], lines[0], lines[2]);
+        assert!(!verify(&reordered, None, Some(&head), None).is_empty());
+
+        let removed = format!("{}\n{}\n", lines[0], lines[2]);
+        let problems = verify(&removed, None, Some(&head), None);
+        assert!(
+            problems[0].contains("seq 3 where 2 was expected"),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn test_detects_truncation() {
+        let (content, head) = build(&LINES, None);
+        let truncated: String = content
+            .lines()
+            .take(2)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let problems = verify(&truncated, None, Some(&head), None);
+        assert_eq!(
+            problems,
+            vec!["log ends at seq 2 but .head says 3 (log truncated)"]
+        );
+    }
+
+    #[test]
+    fn test_hmac_key_required_to_verify() {
+        let (content, head) = build(&LINES, Some(b"secret"));
+        assert!(verify(&content, None, Some(&head), Some(b"secret")).is_empty());
+        assert!(!verify(&content, None, Some(&head), None).is_empty());
+        assert!(!verify(&content, None, Some(&head), Some(b"guess")).is_empty());
+    }
+
+    #[test]
+    fn test_unchained_prefix_is_ignored() {
+        let (sealed, head) = build(&LINES[..1], None);
+        let content = format!("[2025-12-31 23:59:59] ALLOW | git log\n{}", sealed);
+        assert!(verify(&content, None, Some(&head), None).is_empty());
+        assert_eq!(
+            verify(&content, None, None, None),
+            vec![".head is missing (last record is seq 1)"]
+        );
+
+        let content = format!("{}[2026-01-01 00:00:09] ALLOW | git log\n", sealed);
+        let problems = verify(&content, None, Some(&head), None);
+        assert_eq!(problems, vec!["line 2: record has no chain value"]);
+    }
+
+    #[test]
+    fn test_verifies_from_base_after_rotation() {
+        let (content, head) = build(&LINES, None);
+        let (first, rest) = content.split_once('\n').unwrap();
+        let base = last_head(first).unwrap();
+        assert_eq!(base.seq, 1);
+        assert!(verify(rest, Some(&base), Some(&head), None).is_empty());
+
+        // Without the base, the remaining records don't start the chain
+        let problems = verify(rest, None, Some(&head), None);
+        assert!(
+            problems[0].contains("seq 2 where 1 was expected"),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn test_head_round_trip() {
+        let dir = std::env::temp_dir().join("gg_test_chain_head");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("audit.log");
+
+        assert_eq!(Head::read(&log), None);
+        let head = Head {
+            seq: 7,
+            chain: "ab".repeat(32),
+        };
+        head.write(&log, None).unwrap();
+        assert_eq!(Head::read(&log), Some(head.clone()));
+        assert!(dir.join("audit.log.head").exists());
+        // Without a MAC, a key rejects it
+        assert_eq!(Head::read_checked(&log, None), Some((head.clone(), true)));
+        assert_eq!(
+            Head::read_checked(&log, Some(b"k")),
+            Some((head.clone(), false))
+        );
+
+        head.write(&log, Some(b"k")).unwrap();
+        assert_eq!(Head::read(&log), Some(head.clone()));
+        assert_eq!(
+            Head::read_checked(&log, Some(b"k")),
+            Some((head.clone(), true))
+        );
+        assert_eq!(
+            Head::read_checked(&log, Some(b"other")),
+            Some((head.clone(), false))
+        );
+        // A .base copied over .head doesn't pass
+        head.write_base(&log, Some(b"k")).unwrap();
+        assert_eq!(
+            Head::read_base_checked(&log, Some(b"k")),
+            Some((head.clone(), true))
+        );
+        std::fs::copy(Head::base_path(&log), Head::path(&log)).unwrap();
+        assert_eq!(Head::read_checked(&log, Some(b"k")), Some((head, false)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
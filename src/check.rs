@@ -0,0 +1,564 @@
+use crate::config::{Action, Config, Rules};
+use crate::detect::{GH_COMMANDS, GIT_COMMANDS, Tool};
+use crate::repo::{self, REF_KINDS, Update};
+use glob_match::glob_match;
+use std::path::Path;
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Note,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Note => write!(f, "note"),
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub level: Level,
+    pub message: String,
+}
+
+/// A rule as it appears in the config, for reporting
+struct Entry<'a> {
+    tool: Tool,
+    list: &'static str,
+    index: usize,
+    action: Action,
+    /// The string pattern, if the rule has one and no structured conditions
+    pattern: Option<&'a str>,
+    /// First word the rule applies to, if known
+    subcommand: Option<&'a str>,
+    display: String,
+    source: Option<&'a Path>,
+}
+
+impl std::fmt::Display for Entry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let section = if self.list == "rule" { "" } else { "rules." };
+        write!(
+            f,
+            "{}.{}{}[{}] {}",
+            self.tool, section, self.list, self.index, self.display
+        )?;
+        if let Some(source) = self.source {
+            write!(f, " ({})", source.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Lint the resolved config: rules that can never match or never win,
+/// duplicates, unknown subcommands and git/gh overlaps
+pub fn check(config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let git = entries(Tool::Git, &config.git.rules);
+    let gh = entries(Tool::Gh, &config.gh.rules);
+
+    for (tool, rules, entries) in [
+        (Tool::Git, &config.git.rules, &git),
+        (Tool::Gh, &config.gh.rules, &gh),
+    ] {
+        check_never_matches(entries, &mut findings);
+        check_table_rules(tool, rules, &mut findings);
+        check_duplicates(entries, &mut findings);
+        check_shadowed(entries, &mut findings);
+        check_subcommands(entries, &mut findings);
+    }
+    check_overlap(&git, &gh, config, &mut findings);
+
+    findings
+}
+
+/// `gg config check`: print findings; exit 1 if there are warnings or errors
+pub fn run(config: &Config) -> ExitCode {
+    let findings = check(config);
+    for finding in &findings {
+        eprintln!("{}: {}", finding.level, finding.message);
+    }
+    let problems = findings.iter().filter(|f| f.level > Level::Note).count();
+    if problems == 0 {
+        eprintln!("[gg] config check: ok");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("[gg] config check: {} problem(s)", problems);
+        ExitCode::FAILURE
+    }
+}
+
+fn entries(tool: Tool, rules: &Rules) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    for (action, list) in [
+        (Action::Deny, "deny"),
+        (Action::Confirm, "confirm"),
+        (Action::Allow, "allow"),
+    ] {
+        for (index, pattern) in rules.patterns(action).iter().enumerate() {
+            entries.push(Entry {
+                tool,
+                list,
+                index,
+                action,
+                pattern: Some(&pattern.text),
+                subcommand: first_word(&pattern.text),
+                display: format!("{:?}", pattern.text),
+                source: pattern.source.as_deref(),
+            });
+        }
+    }
+    for (index, rule) in rules.matches.iter().enumerate() {
+        let structured = rule.subcommand.is_some()
+            || !rule.any.is_empty()
+            || !rule.all.is_empty()
+            || !rule.none.is_empty()
+            || !rule.positionals.is_empty()
+            || rule.has_repo_conditions();
+        entries.push(Entry {
+            tool,
+            list: "rule",
+            index,
+            action: rule.action,
+            pattern: rule.pattern.as_deref().filter(|_| !structured),
+            subcommand: rule
+                .subcommand
+                .as_deref()
+                .or(rule.pattern.as_deref())
+                .and_then(first_word),
+            display: rule.to_string(),
+            source: rule.source.as_deref(),
+        });
+    }
+    entries
+}
+
+/// The literal first word of a pattern, if it has one
+fn first_word(pattern: &str) -> Option<&str> {
+    let word = pattern.split_whitespace().next()?;
+    let word = word.strip_suffix('*').unwrap_or(word);
+    (!word.is_empty() && !word.contains('*') && !word.starts_with('-')).then_some(word)
+}
+
+fn push(findings: &mut Vec<Finding>, level: Level, message: String) {
+    findings.push(Finding { level, message });
+}
+
+fn check_never_matches(entries: &[Entry], findings: &mut Vec<Finding>) {
+    for entry in entries {
+        let Some(pattern) = entry.pattern else {
+            continue;
+        };
+        let problem = if pattern.trim().is_empty() {
+            Some("is empty")
+        } else if pattern.trim() != pattern {
+            Some("has leading or trailing whitespace")
+        } else if pattern.contains("  ") {
+            Some("contains consecutive spaces, but arguments are joined with single spaces")
+        } else if matches!(first_word(pattern), Some("git" | "gh")) {
+            Some("starts with the tool name, but patterns match the arguments after it")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            push(
+                findings,
+                Level::Error,
+                format!("{} {} and can never match", entry, problem),
+            );
+        }
+    }
+}
+
+fn check_table_rules(tool: Tool, rules: &Rules, findings: &mut Vec<Finding>) {
+    for (index, rule) in rules.matches.iter().enumerate() {
+        let at = format!("{}.rule[{}] {}", tool, index, rule);
+        if rule
+            .subcommand
+            .as_deref()
+            .is_some_and(|s| s.trim().is_empty())
+        {
+            push(
+                findings,
+                Level::Error,
+                format!("{} has an empty subcommand and can never match", at),
+            );
+        }
+        if let Some(both) = rule.all.iter().find(|p| rule.none.contains(p)) {
+            push(
+                findings,
+                Level::Error,
+                format!(
+                    "{} requires and forbids {:?}, so it can never match",
+                    at, both
+                ),
+            );
+        }
+        if let Some(option) = rule.positionals.iter().find(|p| p.starts_with('-')) {
+            push(
+                findings,
+                Level::Error,
+                format!(
+                    "{} expects operand {:?}, but options are never operands",
+                    at, option
+                ),
+            );
+        }
+        for (key, list) in [
+            ("push_target", &rule.push_target),
+            ("push_update", &rule.push_update),
+        ] {
+            if !list.is_empty() && rule.subcommand.as_deref().is_some_and(|s| s != "push") {
+                push(
+                    findings,
+                    Level::Error,
+                    format!(
+                        "{} sets {}, which only matches `push`, so it can never match",
+                        at, key
+                    ),
+                );
+            }
+        }
+        if !rule.remote.is_empty()
+            && rule
+                .subcommand
+                .as_deref()
+                .is_some_and(|s| !["push", "fetch", "pull"].contains(&s))
+        {
+            push(
+                findings,
+                Level::Error,
+                format!(
+                    "{} sets remote, which only matches `push`, `fetch` and `pull`, so it can never match",
+                    at
+                ),
+            );
+        }
+        for entry in &rule.push_update {
+            let pattern = repo::update_pattern(entry);
+            let known = REF_KINDS.iter().any(|kind| {
+                Update::ALL
+                    .iter()
+                    .any(|u| glob_match(&pattern, &format!("{}:{}", kind, u.name())))
+            });
+            if !known {
+                push(
+                    findings,
+                    Level::Error,
+                    format!(
+                        "{} has push_update {:?}, which is no kind of update (create, fast-forward, force, delete)",
+                        at, entry
+                    ),
+                );
+            }
+        }
+        if rule.pattern.is_none()
+            && rule.subcommand.is_none()
+            && rule.any.is_empty()
+            && rule.all.is_empty()
+            && rule.positionals.is_empty()
+            && !rule.has_repo_conditions()
+        {
+            push(
+                findings,
+                Level::Warning,
+                format!("{} has no conditions and matches every command", at),
+            );
+        }
+    }
+}
+
+fn check_duplicates(entries: &[Entry], findings: &mut Vec<Finding>) {
+    for (i, entry) in entries.iter().enumerate() {
+        let Some(earlier) = entries[..i]
+            .iter()
+            .find(|e| e.display == entry.display && e.pattern.is_some() == entry.pattern.is_some())
+        else {
+            continue;
+        };
+        let message = if earlier.action == entry.action {
+            format!("{} duplicates {}", entry, earlier)
+        } else {
+            format!("{} duplicates {}, which is checked first", entry, earlier)
+        };
+        push(findings, Level::Warning, message);
+    }
+}
+
+/// Allow (and confirm) rules that an earlier-evaluated rule always wins over
+fn check_shadowed(entries: &[Entry], findings: &mut Vec<Finding>) {
+    for entry in entries {
+        let Some(pattern) = entry.pattern else {
+            continue;
+        };
+        let shadow = entries.iter().find(|e| {
+            evaluated_before(e.action, entry.action)
+                && e.pattern
+                    .is_some_and(|p| p != pattern && covers(p, pattern))
+        });
+        if let Some(shadow) = shadow {
+            push(
+                findings,
+                Level::Warning,
+                format!(
+                    "{} is unreachable: {} matches everything it does",
+                    entry, shadow
+                ),
+            );
+        }
+    }
+}
+
+fn evaluated_before(a: Action, b: Action) -> bool {
+    let rank = |action| match action {
+        Action::Deny => 0,
+        Action::Confirm => 1,
+        Action::Allow => 2,
+    };
+    rank(a) < rank(b)
+}
+
+/// True if `general` matches every command `specific` matches, judged by the
+/// literal prefix of `specific` (patterns with inner `*` are not compared)
+fn covers(general: &str, specific: &str) -> bool {
+    let literal = specific.split('*').next().unwrap_or_default();
+    match general.find('*') {
+        None => literal == general || literal.starts_with(&format!("{} ", general)),
+        Some(i) if i == general.len() - 1 => literal.starts_with(&general[..i]),
+        Some(_) => false,
+    }
+}
+
+fn check_subcommands(entries: &[Entry], findings: &mut Vec<Finding>) {
+    for entry in entries {
+        let Some(sub) = entry.subcommand else {
+            continue;
+        };
+        let (own, other, other_tool) = match entry.tool {
+            Tool::Git => (GIT_COMMANDS, GH_COMMANDS, Tool::Gh),
+            Tool::Gh => (GH_COMMANDS, GIT_COMMANDS, Tool::Git),
+        };
+        if own.contains(&sub) {
+            continue;
+        }
+        if other.contains(&sub) {
+            push(
+                findings,
+                Level::Warning,
+                format!(
+                    "{}: `{}` is a {} subcommand, not {}",
+                    entry, sub, other_tool, entry.tool
+                ),
+            );
+        } else {
+            push(
+                findings,
+                Level::Note,
+                format!(
+                    "{}: `{}` is not a known {} subcommand; `gg {}` is only auto-detected through rules",
+                    entry, sub, entry.tool, sub
+                ),
+            );
+        }
+    }
+}
+
+fn check_overlap(git: &[Entry], gh: &[Entry], config: &Config, findings: &mut Vec<Finding>) {
+    let mut reported = Vec::new();
+    for entry in git {
+        let Some(sub) = entry.subcommand else {
+            continue;
+        };
+        if reported.contains(&sub) {
+            continue;
+        }
+        if let Some(other) = gh.iter().find(|e| e.subcommand == Some(sub)) {
+            reported.push(sub);
+            push(
+                findings,
+                Level::Note,
+                format!(
+                    "`{}` has both git and gh rules ({} / {}); auto-detection picks priority = {}",
+                    sub, entry, other, config.options.priority
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn findings(toml: &str) -> Vec<(Level, String)> {
+        let config = Config::from_str(toml).unwrap();
+        check(&config)
+            .into_iter()
+            .map(|f| (f.level, f.message))
+            .collect()
+    }
+
+    fn has(findings: &[(Level, String)], level: Level, needle: &str) -> bool {
+        findings
+            .iter()
+            .any(|(l, m)| *l == level && m.contains(needle))
+    }
+
+    #[test]
+    fn test_clean_config() {
+        let found = findings(
+            r#"
+[git.rules]
+allow = ["status*", "log*"]
+deny = ["push --force*"]
+"#,
+        );
+        assert!(found.iter().all(|(l, _)| *l == Level::Note), "{:?}", found);
+    }
+
+    #[test]
+    fn test_shadowed_allow() {
+        let found = findings(
+            r#"
+[git.rules]
+allow = ["push origin*", "pr merge*"]
+confirm = ["push"]
+"#,
+        );
+        assert!(has(
+            &found,
+            Level::Warning,
+            r#"git.rules.allow[0] "push origin*" is unreachable: git.rules.confirm[0] "push""#
+        ));
+    }
+
+    #[test]
+    fn test_never_matches() {
+        let found = findings(
+            r#"
+[git.rules]
+deny = ["git push --force*", "reset  --hard", " clean*"]
+
+[[git.rule]]
+action = "deny"
+subcommand = "clean"
+all = ["-f"]
+none = ["-f"]
+"#,
+        );
+        assert!(has(&found, Level::Error, "starts with the tool name"));
+        assert!(has(&found, Level::Error, "consecutive spaces"));
+        assert!(has(&found, Level::Error, "leading or trailing whitespace"));
+        assert!(has(&found, Level::Error, "requires and forbids \"-f\""));
+    }
+
+    #[test]
+    fn test_branch_conditions() {
+        let found = findings(
+            r#"
+[[git.rule]]
+action = "deny"
+subcommand = "commit"
+push_target = ["main"]
+
+[[git.rule]]
+action = "confirm"
+branch = ["${default_branch}"]
+
+[[git.rule]]
+action = "deny"
+subcommand = "fetch"
+remote = ["github.com/upstream/*"]
+"#,
+        );
+        assert!(has(&found, Level::Error, "sets push_target"));
+        assert!(!has(&found, Level::Error, "sets remote"));
+        assert!(!has(&found, Level::Error, "push_update"));
+
+        let found = findings(
+            r#"
+[[git.rule]]
+action = "deny"
+subcommand = "push"
+push_update = ["force", "tag:*", "forced", "tags:delete"]
+
+[[git.rule]]
+action = "deny"
+subcommand = "commit"
+remote = ["github.com/upstream/*"]
+"#,
+        );
+        assert!(has(&found, Level::Error, r#"push_update "forced""#));
+        assert!(has(&found, Level::Error, r#"push_update "tags:delete""#));
+        assert!(!has(&found, Level::Error, r#"push_update "force""#));
+        assert!(!has(&found, Level::Error, r#"push_update "tag:*""#));
+        assert!(has(&found, Level::Error, "sets remote"));
+        assert!(!has(&found, Level::Warning, "has no conditions"));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let found = findings(
+            r#"
+[gh.rules]
+allow = ["pr list*", "pr merge*"]
+deny = ["pr merge*", "pr list*", "pr list*"]
+"#,
+        );
+        assert!(has(
+            &found,
+            Level::Warning,
+            r#"gh.rules.deny[2] "pr list*" duplicates gh.rules.deny[1] "pr list*""#
+        ));
+        assert!(has(
+            &found,
+            Level::Warning,
+            r#"gh.rules.allow[1] "pr merge*" duplicates gh.rules.deny[0] "pr merge*", which is checked first"#
+        ));
+    }
+
+    #[test]
+    fn test_subcommands_and_overlap() {
+        let found = findings(
+            r#"
+[git.rules]
+allow = ["status", "pr list*", "version"]
+
+[gh.rules]
+allow = ["status"]
+"#,
+        );
+        assert!(has(
+            &found,
+            Level::Warning,
+            "`pr` is a gh subcommand, not git"
+        ));
+        assert!(has(
+            &found,
+            Level::Note,
+            "`version` is not a known git subcommand"
+        ));
+        assert!(has(
+            &found,
+            Level::Note,
+            "`status` has both git and gh rules"
+        ));
+    }
+
+    #[test]
+    fn test_covers() {
+        assert!(covers("push", "push origin*"));
+        assert!(covers("push*", "push --force*"));
+        assert!(covers("pr *", "pr merge*"));
+        assert!(!covers("push --force*", "push*"));
+        assert!(!covers("push", "pushx"));
+        assert!(!covers("api * /x", "api GET /x"));
+    }
+}
@@ -1,32 +1,255 @@
+use crate::rules::Decision;
+use regex::Regex;
 use serde::Deserialize;
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Config files (or `preset:<name>`) this one builds on
+    #[serde(default)]
+    pub extends: Vec<String>,
     #[serde(default)]
     pub git: ToolConfig,
     #[serde(default)]
     pub gh: ToolConfig,
     #[serde(default)]
     pub options: Options,
+    /// The `[options]` keys this file and its bases actually set, so that
+    /// merging a layer doesn't mistake serde defaults for its settings
+    #[serde(skip)]
+    pub set_options: toml::Table,
 }
 
 #[derive(Debug, Default, Deserialize)]
+#[serde(from = "ToolConfigFile")]
 pub struct ToolConfig {
-    #[serde(default)]
     pub rules: Rules,
 }
 
+/// On-disk form of a tool section; `[[git.rule]]` tables are folded into
+/// `rules.matches` so both spellings are evaluated the same way.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolConfigFile {
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    rule: Vec<MatchRule>,
+}
+
+impl From<ToolConfigFile> for ToolConfig {
+    fn from(file: ToolConfigFile) -> Self {
+        let mut rules = file.rules;
+        rules.matches.extend(file.rule);
+        Self { rules }
+    }
+}
+
 #[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Rules {
     #[serde(default)]
-    pub allow: Vec<String>,
+    pub allow: Vec<Pattern>,
     #[serde(default)]
-    pub confirm: Vec<String>,
+    pub confirm: Vec<Pattern>,
     #[serde(default)]
-    pub deny: Vec<String>,
+    pub deny: Vec<Pattern>,
+    /// Table rules (`[[git.rules.match]]` / `[[git.rule]]`)
+    #[serde(default, rename = "match")]
+    pub matches: Vec<MatchRule>,
+}
+
+impl Rules {
+    pub fn patterns(&self, action: Action) -> &[Pattern] {
+        match action {
+            Action::Allow => &self.allow,
+            Action::Confirm => &self.confirm,
+            Action::Deny => &self.deny,
+        }
+    }
+
+    fn set_source(&mut self, path: &Path) {
+        for pattern in self
+            .allow
+            .iter_mut()
+            .chain(self.confirm.iter_mut())
+            .chain(self.deny.iter_mut())
+        {
+            pattern.source = Some(path.to_path_buf());
+        }
+        for rule in &mut self.matches {
+            rule.source = Some(path.to_path_buf());
+        }
+    }
+}
+
+/// A string rule pattern and the config file it was loaded from
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(from = "String")]
+pub struct Pattern {
+    pub text: String,
+    pub source: Option<PathBuf>,
+}
+
+impl From<String> for Pattern {
+    fn from(text: String) -> Self {
+        Self { text, source: None }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
+
+impl Deref for Pattern {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Confirm,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A table rule. `pattern` uses the string pattern syntax; the other match
+/// fields are checked against the parsed command. Every field that is set
+/// must hold; globs use the same `*` syntax as patterns.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatchRule {
+    pub action: Action,
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Shown when the rule blocks or asks for confirmation
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// Alternative command offered when the rule blocks, e.g. `push --force-with-lease`
+    #[serde(default)]
+    pub suggest: Option<String>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Leading words, e.g. `"push"` or `"pr merge"`
+    #[serde(default)]
+    pub subcommand: Option<String>,
+    /// At least one argument matches one of these
+    #[serde(default)]
+    pub any: Vec<String>,
+    /// Every entry matches some argument
+    #[serde(default)]
+    pub all: Vec<String>,
+    /// No argument matches any of these
+    #[serde(default)]
+    pub none: Vec<String>,
+    /// Operands after the subcommand, matched in order
+    #[serde(default)]
+    pub positionals: Vec<String>,
+    /// The current branch matches one of these. In these and the other
+    /// branch conditions, `${default_branch}` is the branch origin/HEAD
+    /// points at, else `init.defaultBranch`, else `main` or `master`.
+    #[serde(default)]
+    pub branch: Vec<String>,
+    /// The current branch's upstream branch matches one of these
+    #[serde(default)]
+    pub upstream: Vec<String>,
+    /// A branch the push would update matches one of these
+    #[serde(default)]
+    pub push_target: Vec<String>,
+    /// A ref update the push would make matches one of these: `create`,
+    /// `fast-forward`, `force` or `delete`, optionally qualified by kind as
+    /// in `tag:force`
+    #[serde(default)]
+    pub push_update: Vec<String>,
+    /// The URL a push, fetch or pull talks to matches one of these, written
+    /// as `host/path`, e.g. `github.com/our-org/*`
+    #[serde(default)]
+    pub remote: Vec<String>,
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+impl MatchRule {
+    /// Whether matching needs to ask git about the repository
+    pub fn has_repo_conditions(&self) -> bool {
+        !self.branch.is_empty()
+            || !self.upstream.is_empty()
+            || !self.push_target.is_empty()
+            || !self.push_update.is_empty()
+            || !self.remote.is_empty()
+    }
+}
+
+impl fmt::Display for MatchRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(pattern) = &self.pattern {
+            parts.push(format!("{:?}", pattern));
+        }
+        if let Some(subcommand) = &self.subcommand {
+            parts.push(format!("subcommand={:?}", subcommand));
+        }
+        for (key, list) in [
+            ("any", &self.any),
+            ("all", &self.all),
+            ("none", &self.none),
+            ("positionals", &self.positionals),
+            ("branch", &self.branch),
+            ("upstream", &self.upstream),
+            ("push_target", &self.push_target),
+            ("push_update", &self.push_update),
+            ("remote", &self.remote),
+        ] {
+            if !list.is_empty() {
+                parts.push(format!("{}={:?}", key, list));
+            }
+        }
+        write!(f, "{}", parts.join(" "))
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
@@ -47,6 +270,7 @@ impl fmt::Display for Priority {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Options {
     #[serde(default = "default_true")]
     pub log: bool,
@@ -56,6 +280,69 @@ pub struct Options {
     pub priority: Priority,
     #[serde(default)]
     pub log_file: Option<String>,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Seal each log record with a hash of the previous one
+    #[serde(default)]
+    pub log_chain: bool,
+    /// Key for HMAC-sealing the chain; keep it where agents can't read it
+    #[serde(default)]
+    pub log_hmac_key_file: Option<String>,
+    /// Rotate the log once it reaches this size: bytes, or e.g. `"10MB"`
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub log_max_size: Option<u64>,
+    /// Rotated logs kept as `<log_file>.1` (newest) to `<log_file>.N`
+    #[serde(default = "default_log_keep")]
+    pub log_keep: usize,
+    /// Gzip rotated logs
+    #[serde(default)]
+    pub log_compress: bool,
+    /// Extra regexes masked in logged and notified commands, on top of the
+    /// built-in secret detectors
+    #[serde(default, deserialize_with = "deserialize_regexes")]
+    pub log_redact: Vec<Regex>,
+    /// Where audit records go: the log file, syslog, or both
+    #[serde(default)]
+    pub log_sink: LogSink,
+    /// Syslog socket, `/dev/log` by default
+    #[serde(default)]
+    pub log_syslog_socket: Option<String>,
+    /// How to ask for confirmation, tried in order until one is available
+    #[serde(default = "default_confirm_via")]
+    pub confirm_via: Vec<ConfirmChannel>,
+    /// Socket of `gg confirm serve`, `~/.local/share/gg/confirm.sock` by
+    /// default
+    #[serde(default)]
+    pub confirm_socket: Option<String>,
+    /// Command that approves by exiting 0, e.g. a desktop dialog
+    #[serde(default)]
+    pub confirm_command: Vec<String>,
+    /// Deny if nobody answers within this many seconds (socket and command).
+    /// At least 1: a zero timeout couldn't wait for anyone.
+    #[serde(
+        default = "default_confirm_timeout_secs",
+        deserialize_with = "deserialize_timeout"
+    )]
+    pub confirm_timeout_secs: u64,
+    /// POST a JSON notification here when a command is blocked or needs
+    /// confirmation
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Decisions that trigger the webhook
+    #[serde(default = "default_webhook_on")]
+    pub webhook_on: Vec<Decision>,
+    /// Give up on the webhook after this long, so a dead endpoint can't hold
+    /// up the command
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub webhook_timeout_ms: u64,
+    /// Combine the global config with `$GG_CONFIG` and `./gg.toml` instead of
+    /// using the first file found. Only read from the global config.
+    #[serde(default)]
+    pub merge: bool,
+    #[serde(default)]
+    pub merge_allow: MergeAllow,
+    #[serde(default)]
+    pub output: Output,
 }
 
 impl Default for Options {
@@ -65,63 +352,513 @@ impl Default for Options {
             deny_by_default: true,
             priority: Priority::default(),
             log_file: None,
+            log_format: LogFormat::default(),
+            log_chain: false,
+            log_hmac_key_file: None,
+            log_max_size: None,
+            log_keep: default_log_keep(),
+            log_compress: false,
+            log_redact: Vec::new(),
+            log_sink: LogSink::default(),
+            log_syslog_socket: None,
+            confirm_via: default_confirm_via(),
+            confirm_socket: None,
+            confirm_command: Vec::new(),
+            confirm_timeout_secs: default_confirm_timeout_secs(),
+            webhook_url: None,
+            webhook_on: default_webhook_on(),
+            webhook_timeout_ms: default_webhook_timeout_ms(),
+            merge: false,
+            merge_allow: MergeAllow::default(),
+            output: Output::default(),
         }
     }
 }
 
+/// Format of audit log lines
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `[ts] DECISION | tool cmd | rule ...`
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Where a `confirm` rule's question is asked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmChannel {
+    /// Prompt on stdin, if it is a terminal
+    Stdin,
+    /// Prompt on the controlling terminal, even when stdin is a pipe
+    Tty,
+    /// Ask `gg confirm serve` running in another terminal
+    Socket,
+    /// Run `confirm_command`
+    Command,
+}
+
+/// Destination of audit records
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSink {
+    #[default]
+    File,
+    /// RFC 5424 messages to the local syslog socket
+    Syslog,
+    Both,
+}
+
+impl LogSink {
+    pub fn file(self) -> bool {
+        matches!(self, LogSink::File | LogSink::Both)
+    }
+
+    pub fn syslog(self) -> bool {
+        matches!(self, LogSink::Syslog | LogSink::Both)
+    }
+}
+
+/// Format of gg's own messages on stderr
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    #[default]
+    Text,
+    /// One JSON object per block, confirmation or detection failure
+    Json,
+}
+
+impl Output {
+    /// `GG_OUTPUT=json|text` overrides the configured format
+    pub fn resolve(configured: Output) -> Output {
+        match std::env::var("GG_OUTPUT").as_deref() {
+            Ok("json") => Output::Json,
+            Ok("text") => Output::Text,
+            _ => configured,
+        }
+    }
+}
+
+/// How allow lists from local layers combine with the global one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeAllow {
+    /// Local allow rules are added to the global ones
+    #[default]
+    Append,
+    /// Only global allow rules that a local layer also lists are kept
+    Intersect,
+}
+
+/// `~/.local/share/gg`, where gg keeps its log and state
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".local").join("share").join("gg"))
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_log_keep() -> usize {
+    5
+}
+
+fn default_confirm_via() -> Vec<ConfirmChannel> {
+    vec![ConfirmChannel::Stdin]
+}
+
+fn default_confirm_timeout_secs() -> u64 {
+    120
+}
+
+fn default_webhook_on() -> Vec<Decision> {
+    vec![Decision::Deny, Decision::DefaultDeny, Decision::Confirm]
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    2000
+}
+
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(Some(bytes)),
+        Size::Text(text) => parse_size(&text).map(Some).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "invalid size `{}`, expected e.g. 1048576, \"512KB\" or \"10MB\"",
+                text
+            ))
+        }),
+    }
+}
+
+fn deserialize_timeout<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match u64::deserialize(deserializer)? {
+        0 => Err(serde::de::Error::custom(
+            "confirm_timeout_secs must be at least 1",
+        )),
+        secs => Ok(secs),
+    }
+}
+
+fn deserialize_regexes<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| {
+                serde::de::Error::custom(format!("invalid regex `{}`: {}", pattern, e))
+            })
+        })
+        .collect()
+}
+
+/// `"512"`, `"512K"`, `"10MB"`, `"1 GiB"`; units are powers of 1024
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return None,
+    };
+    number.checked_mul(1 << shift)
+}
+
 impl Config {
     #[cfg(test)]
     pub fn from_str(s: &str) -> Result<Self, toml::de::Error> {
-        toml::from_str(s)
+        let mut config: Config = toml::from_str(s)?;
+        let raw: toml::Table = toml::from_str(s)?;
+        if let Some(toml::Value::Table(options)) = raw.get("options") {
+            config.set_options = options.clone();
+        }
+        Ok(config)
     }
 
-    pub fn load() -> Self {
-        Self::load_inner(std::env::var("GG_VERBOSE").is_ok())
+    /// Load the effective config. Unless `GG_NO_STRICT` is set, a config file
+    /// that cannot be read or parsed is an error rather than skipped, so a typo
+    /// never silently changes which policy is enforced.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_inner(
+            std::env::var("GG_VERBOSE").is_ok(),
+            std::env::var("GG_NO_STRICT").is_err(),
+        )
     }
 
-    fn load_inner(verbose: bool) -> Self {
-        let paths = config_search_paths();
-        for path in &paths {
-            if let Ok(content) = fs::read_to_string(path) {
-                match toml::from_str::<Config>(&content) {
-                    Ok(config) => {
-                        if verbose {
-                            eprintln!("[gg] config loaded from {}", path.display());
-                        }
-                        return config;
-                    }
-                    Err(e) => {
-                        eprintln!("[gg] config parse error in {}: {}", path.display(), e);
-                    }
+    fn load_inner(verbose: bool, strict: bool) -> Result<Self, ConfigError> {
+        let load = |path: &PathBuf| match Self::load_file(path, verbose) {
+            Err(e) if !strict => {
+                eprintln!("[gg] {}", e);
+                Ok(None)
+            }
+            result => result,
+        };
+        let local = local_config_paths();
+        let global = global_config_paths();
+
+        let mut base = None;
+        for path in &global {
+            if let Some(config) = load(path)? {
+                base = Some(config);
+                break;
+            }
+        }
+
+        if let Some(config) = base.as_mut().filter(|c| c.options.merge) {
+            // Lowest precedence first: $GG_CONFIG, then ./gg.toml
+            for path in local.iter().rev() {
+                if let Some(layer) = load(path)? {
+                    config.merge(layer);
+                }
+            }
+        } else {
+            for path in &local {
+                if let Some(config) = load(path)? {
+                    return Ok(config);
                 }
             }
         }
+
+        Ok(base.unwrap_or_else(|| {
+            if verbose {
+                eprintln!("[gg] no config found, using defaults (deny all)");
+            }
+            Config::default()
+        }))
+    }
+
+    /// Ok(None) if the file does not exist
+    fn load_file(path: &Path, verbose: bool) -> Result<Option<Self>, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(ConfigError::Read {
+                    path: path.to_path_buf(),
+                    error,
+                });
+            }
+        };
+        let (config, _) = Self::resolve(path, &content, &mut Vec::new())?;
         if verbose {
-            eprintln!("[gg] no config found, using defaults (deny all)");
+            eprintln!("[gg] config loaded from {}", path.display());
+        }
+        Ok(Some(config))
+    }
+
+    /// Parse a config file or preset along with everything it extends.
+    /// Rules from bases come first and are appended to; options a file sets
+    /// override those of its bases. Returns the config and its raw options
+    /// table so that files extending this one can do the same.
+    fn resolve(
+        path: &Path,
+        content: &str,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(Self, toml::Table), ConfigError> {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if stack.contains(&key) {
+            let mut cycle = stack.clone();
+            cycle.push(key);
+            return Err(ConfigError::Cycle(cycle));
+        }
+
+        let parse_error = |error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            error,
+        };
+        let mut own: Config = toml::from_str(content).map_err(parse_error)?;
+        let raw: toml::Table = toml::from_str(content).map_err(parse_error)?;
+        own.git.rules.set_source(path);
+        own.gh.rules.set_source(path);
+
+        let mut options = match raw.get("options") {
+            Some(toml::Value::Table(t)) => t.clone(),
+            _ => toml::Table::new(),
+        };
+        if own.extends.is_empty() {
+            own.set_options = options.clone();
+            return Ok((own, options));
+        }
+
+        stack.push(key);
+        let mut config = Config::default();
+        let mut base_options = toml::Table::new();
+        for name in &own.extends {
+            let (base_path, base_content) = locate(name, path)?;
+            let (base, opts) = Self::resolve(&base_path, &base_content, stack)?;
+            config.extend(base);
+            base_options.extend(opts);
+        }
+        stack.pop();
+
+        base_options.extend(options);
+        options = base_options;
+        config.extend(own);
+        config.options = toml::Value::Table(options.clone())
+            .try_into()
+            .map_err(parse_error)?;
+        config.set_options = options.clone();
+        Ok((config, options))
+    }
+
+    /// Append another config's rules to this one's
+    fn extend(&mut self, other: Config) {
+        self.extends.extend(other.extends);
+        self.git.rules.merge(other.git.rules, MergeAllow::Append);
+        self.gh.rules.merge(other.gh.rules, MergeAllow::Append);
+    }
+
+    /// Layer a local config on top of this (global) one. Local layers can only
+    /// tighten: deny and confirm rules are unioned, `deny_by_default` and `log`
+    /// can be switched on but not off, and all other options stay global.
+    fn merge(&mut self, layer: Config) {
+        let allow = self.options.merge_allow;
+        self.git.rules.merge(layer.git.rules, allow);
+        self.gh.rules.merge(layer.gh.rules, allow);
+        let sets = |key: &str| layer.set_options.contains_key(key);
+        if sets("deny_by_default") {
+            self.options.deny_by_default |= layer.options.deny_by_default;
+        }
+        if sets("log") {
+            self.options.log |= layer.options.log;
         }
-        Config::default()
     }
 }
 
-pub fn config_search_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    let no_local = std::env::var("GG_NO_LOCAL").is_ok();
+impl Rules {
+    fn merge(&mut self, layer: Rules, allow: MergeAllow) {
+        union(&mut self.deny, layer.deny);
+        union(&mut self.confirm, layer.confirm);
 
-    // 1. Current directory (skipped if GG_NO_LOCAL is set)
-    if !no_local {
-        paths.push(PathBuf::from("gg.toml"));
+        let (layer_allow, layer_tables): (Vec<_>, Vec<_>) = layer
+            .matches
+            .into_iter()
+            .partition(|r| r.action == Action::Allow);
+        for rule in layer_tables {
+            if !self.matches.iter().any(|r| r.same_as(&rule)) {
+                self.matches.push(rule);
+            }
+        }
+
+        match allow {
+            MergeAllow::Append => {
+                union(&mut self.allow, layer.allow);
+                for rule in layer_allow {
+                    if !self.matches.iter().any(|r| r.same_as(&rule)) {
+                        self.matches.push(rule);
+                    }
+                }
+            }
+            MergeAllow::Intersect => {
+                if layer.allow.is_empty() && layer_allow.is_empty() {
+                    return;
+                }
+                self.allow
+                    .retain(|p| layer.allow.iter().any(|q| q.text == p.text));
+                self.matches.retain(|r| {
+                    r.action != Action::Allow || layer_allow.iter().any(|q| q.same_as(r))
+                });
+            }
+        }
+    }
+}
+
+impl MatchRule {
+    /// Same action and match conditions, ignoring metadata and source
+    fn same_as(&self, other: &MatchRule) -> bool {
+        self.action == other.action && self.to_string() == other.to_string()
+    }
+}
+
+/// Built-in policies, usable as `extends = ["preset:<name>"]`
+const PRESETS: &[(&str, &str)] = &[
+    ("read-only", include_str!("presets/read-only.toml")),
+    ("contributor", include_str!("presets/contributor.toml")),
+    ("maintainer", include_str!("presets/maintainer.toml")),
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    UnknownPreset {
+        name: String,
+        from: PathBuf,
+    },
+    Cycle(Vec<PathBuf>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } => {
+                write!(f, "cannot read config {}: {}", path.display(), error)
+            }
+            ConfigError::Parse { path, error } => {
+                write!(f, "config parse error in {}: {}", path.display(), error)
+            }
+            ConfigError::UnknownPreset { name, from } => {
+                let available: Vec<&str> = PRESETS.iter().map(|(n, _)| *n).collect();
+                write!(
+                    f,
+                    "unknown preset `{}` in {} (available: {})",
+                    name,
+                    from.display(),
+                    available.join(", ")
+                )
+            }
+            ConfigError::Cycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "config extends cycle: {}", chain.join(" -> "))
+            }
+        }
+    }
+}
+
+/// Find an `extends` entry: a built-in preset, or a path that may start with
+/// `~/` and is otherwise relative to the file that names it
+fn locate(name: &str, from: &Path) -> Result<(PathBuf, String), ConfigError> {
+    if let Some(preset) = name.strip_prefix("preset:") {
+        return PRESETS
+            .iter()
+            .find(|(n, _)| *n == preset)
+            .map(|(_, content)| (PathBuf::from(name), content.to_string()))
+            .ok_or_else(|| ConfigError::UnknownPreset {
+                name: preset.to_string(),
+                from: from.to_path_buf(),
+            });
     }
 
-    // 2. $GG_CONFIG env (skipped if GG_NO_LOCAL is set)
-    if !no_local {
-        if let Ok(p) = std::env::var("GG_CONFIG") {
-            paths.push(PathBuf::from(p));
+    let path = match name.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None if from.starts_with("preset:") => PathBuf::from(name),
+        None => from.parent().unwrap_or(Path::new("")).join(name),
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok((path, content)),
+        Err(error) => Err(ConfigError::Read { path, error }),
+    }
+}
+
+/// Append patterns not already present (by text)
+fn union(list: &mut Vec<Pattern>, more: Vec<Pattern>) {
+    for pattern in more {
+        if !list.iter().any(|p| p.text == pattern.text) {
+            list.push(pattern);
         }
     }
+}
+
+/// Project-level configs, in precedence order (skipped if GG_NO_LOCAL is set)
+fn local_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if std::env::var("GG_NO_LOCAL").is_ok() {
+        return paths;
+    }
+
+    // 1. Current directory
+    paths.push(PathBuf::from("gg.toml"));
+
+    // 2. $GG_CONFIG env
+    if let Ok(p) = std::env::var("GG_CONFIG") {
+        paths.push(PathBuf::from(p));
+    }
+
+    paths
+}
+
+/// User-level configs, in precedence order
+fn global_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
 
     if let Some(home) = dirs::home_dir() {
         // 3. ~/.config/gg/config.toml (XDG-style)
@@ -203,6 +940,150 @@ deny = ["repo delete*"]
         assert_eq!(config.gh.rules.deny.len(), 1);
     }
 
+    #[test]
+    fn test_parse_match_rules() {
+        let toml = r#"
+[[git.rules.match]]
+action = "deny"
+subcommand = "push"
+any = ["--force*", "-f", "+*"]
+
+[[gh.rules.match]]
+action = "confirm"
+subcommand = "pr merge"
+none = ["--auto"]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.git.rules.matches.len(), 1);
+        let rule = &config.git.rules.matches[0];
+        assert_eq!(rule.action, Action::Deny);
+        assert_eq!(rule.subcommand.as_deref(), Some("push"));
+        assert_eq!(rule.any.len(), 3);
+        assert_eq!(config.gh.rules.matches[0].action, Action::Confirm);
+        assert_eq!(config.gh.rules.matches[0].none, vec!["--auto"]);
+    }
+
+    #[test]
+    fn test_set_source() {
+        let mut config = Config::from_str(
+            r#"
+[git.rules]
+deny = ["push --force*"]
+
+[[git.rule]]
+pattern = "reset --hard*"
+action = "deny"
+"#,
+        )
+        .unwrap();
+        let path = PathBuf::from("/etc/gg.toml");
+        config.git.rules.set_source(&path);
+        assert_eq!(config.git.rules.deny[0].source.as_ref(), Some(&path));
+        assert_eq!(config.git.rules.matches[0].source.as_ref(), Some(&path));
+        assert!(config.gh.rules.deny.is_empty());
+    }
+
+    #[test]
+    fn test_match_rule_display() {
+        let config = Config::from_str(
+            r#"
+[[git.rule]]
+action = "deny"
+subcommand = "push"
+any = ["--force*", "-f"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.git.rules.matches[0].to_string(),
+            r#"subcommand="push" any=["--force*", "-f"]"#
+        );
+    }
+
+    #[test]
+    fn test_parse_table_rules() {
+        let toml = r#"
+[git.rules]
+deny = ["reset --hard*"]
+
+[[git.rule]]
+pattern = "push --force*"
+action = "deny"
+reason = "force-push rewrites shared history"
+id = "no-force-push"
+severity = "high"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.git.rules.deny.len(), 1);
+        assert_eq!(config.git.rules.matches.len(), 1);
+        let rule = &config.git.rules.matches[0];
+        assert_eq!(rule.pattern.as_deref(), Some("push --force*"));
+        assert_eq!(rule.id.as_deref(), Some("no-force-push"));
+        assert_eq!(rule.severity, Some(Severity::High));
+        assert_eq!(
+            rule.reason.as_deref(),
+            Some("force-push rewrites shared history")
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        for toml in [
+            "[options]\ndeny_by_defualt = false",
+            "[git.rules]\nalow = [\"status\"]",
+            "[gh]\nrule = []\nrulez = []",
+            "[[git.rule]]\naction = \"deny\"\npatern = \"push*\"",
+            "extend = [\"preset:read-only\"]",
+        ] {
+            assert!(Config::from_str(toml).is_err(), "{}", toml);
+        }
+        let err = Config::from_str("[options]\ndeny_by_defualt = false").unwrap_err();
+        assert!(err.to_string().contains("unknown field `deny_by_defualt`"));
+    }
+
+    #[test]
+    fn test_parse_log_rotation() {
+        let config = Config::from_str("[options]\nlog_max_size = \"10MB\"\nlog_keep = 2").unwrap();
+        assert_eq!(config.options.log_max_size, Some(10 << 20));
+        assert_eq!(config.options.log_keep, 2);
+        assert!(!config.options.log_compress);
+
+        let config = Config::from_str("[options]\nlog_max_size = 4096").unwrap();
+        assert_eq!(config.options.log_max_size, Some(4096));
+        assert_eq!(config.options.log_keep, 5);
+
+        let err = Config::from_str("[options]\nlog_max_size = \"10 parsecs\"").unwrap_err();
+        assert!(err.to_string().contains("invalid size `10 parsecs`"));
+    }
+
+    #[test]
+    fn test_parse_log_redact() {
+        let config = Config::from_str("[options]\nlog_redact = ['sk-[a-z0-9]+']").unwrap();
+        assert!(config.options.log_redact[0].is_match("sk-abc123"));
+
+        let err = Config::from_str("[options]\nlog_redact = ['(unclosed']").unwrap_err();
+        assert!(err.to_string().contains("invalid regex `(unclosed`"));
+    }
+
+    #[test]
+    fn test_parse_confirm_timeout() {
+        let config = Config::from_str("[options]\nconfirm_timeout_secs = 30").unwrap();
+        assert_eq!(config.options.confirm_timeout_secs, 30);
+
+        let err = Config::from_str("[options]\nconfirm_timeout_secs = 0").unwrap_err();
+        assert!(err.to_string().contains("must be at least 1"));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("512K"), Some(512 << 10));
+        assert_eq!(parse_size("1 GiB"), Some(1 << 30));
+        assert_eq!(parse_size("10mb"), Some(10 << 20));
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("1.5MB"), None);
+    }
+
     #[test]
     fn test_parse_invalid_toml() {
         let result = Config::from_str("invalid = [[[");
@@ -215,9 +1096,266 @@ deny = ["repo delete*"]
         assert_eq!(format!("{}", Priority::Gh), "gh");
     }
 
+    fn merged(global: &str, local: &str) -> Config {
+        let mut config = Config::from_str(global).unwrap();
+        config.merge(Config::from_str(local).unwrap());
+        config
+    }
+
+    fn texts(patterns: &[Pattern]) -> Vec<&str> {
+        patterns.iter().map(|p| p.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_merge_unions_deny_and_appends_allow() {
+        let config = merged(
+            r#"
+[options]
+merge = true
+[git.rules]
+allow = ["status*"]
+deny = ["push --force*"]
+"#,
+            r#"
+[git.rules]
+allow = ["log*", "status*"]
+deny = ["reset --hard*"]
+"#,
+        );
+        assert_eq!(texts(&config.git.rules.allow), vec!["status*", "log*"]);
+        assert_eq!(
+            texts(&config.git.rules.deny),
+            vec!["push --force*", "reset --hard*"]
+        );
+    }
+
+    #[test]
+    fn test_merge_intersect_allow() {
+        let config = merged(
+            r#"
+[options]
+merge = true
+merge_allow = "intersect"
+[git.rules]
+allow = ["status*", "log*", "diff*"]
+[gh.rules]
+allow = ["pr list*"]
+"#,
+            r#"
+[git.rules]
+allow = ["log*", "push*"]
+"#,
+        );
+        assert_eq!(texts(&config.git.rules.allow), vec!["log*"]);
+        // A layer without allow rules leaves the global list alone
+        assert_eq!(texts(&config.gh.rules.allow), vec!["pr list*"]);
+    }
+
+    #[test]
+    fn test_merge_local_cannot_loosen() {
+        let config = merged(
+            r#"
+[options]
+merge = true
+deny_by_default = true
+log_file = "/var/log/gg.log"
+[git.rules]
+deny = ["push --force*"]
+"#,
+            r#"
+[options]
+deny_by_default = false
+log = false
+log_file = "/dev/null"
+merge_allow = "append"
+[git.rules]
+allow = ["push --force*"]
+"#,
+        );
+        assert!(config.options.deny_by_default);
+        assert!(config.options.log);
+        assert_eq!(config.options.log_file.as_deref(), Some("/var/log/gg.log"));
+        assert_eq!(texts(&config.git.rules.deny), vec!["push --force*"]);
+    }
+
+    #[test]
+    fn test_merge_keeps_global_options_a_layer_does_not_set() {
+        let global = r#"
+[options]
+merge = true
+deny_by_default = false
+log = false
+"#;
+        let config = merged(
+            global,
+            r#"
+[git.rules]
+deny = ["push --force*"]
+"#,
+        );
+        assert!(!config.options.deny_by_default);
+        assert!(!config.options.log);
+
+        let config = merged(
+            global,
+            r#"
+[options]
+deny_by_default = true
+"#,
+        );
+        assert!(config.options.deny_by_default);
+        assert!(!config.options.log);
+    }
+
+    #[test]
+    fn test_merge_table_rules() {
+        let config = merged(
+            r#"
+[options]
+merge = true
+merge_allow = "intersect"
+[[git.rule]]
+action = "deny"
+pattern = "push --force*"
+[[git.rule]]
+action = "allow"
+pattern = "status*"
+"#,
+            r#"
+[[git.rule]]
+action = "deny"
+pattern = "push --force*"
+reason = "duplicate"
+[[git.rule]]
+action = "deny"
+pattern = "clean*"
+[[git.rule]]
+action = "allow"
+pattern = "log*"
+"#,
+        );
+        let rules: Vec<String> = config
+            .git
+            .rules
+            .matches
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        assert_eq!(rules, vec![r#""push --force*""#, r#""clean*""#]);
+    }
+
+    fn resolve_file(path: &Path) -> Result<Config, ConfigError> {
+        let content = fs::read_to_string(path).unwrap();
+        Config::resolve(path, &content, &mut Vec::new()).map(|(c, _)| c)
+    }
+
+    #[test]
+    fn test_presets_parse() {
+        for (name, _) in PRESETS {
+            let content = format!("extends = [\"preset:{}\"]", name);
+            let (config, _) =
+                Config::resolve(Path::new("gg.toml"), &content, &mut Vec::new()).unwrap();
+            assert!(config.options.deny_by_default, "{}", name);
+            assert!(!config.git.rules.allow.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_extends_preset_chain() {
+        let content = r#"
+extends = ["preset:maintainer"]
+
+[git.rules]
+deny = ["rebase*"]
+"#;
+        let (config, _) = Config::resolve(Path::new("gg.toml"), content, &mut Vec::new()).unwrap();
+        let rules = &config.git.rules;
+        // read-only → contributor → maintainer → this file
+        assert!(rules.allow.iter().any(|p| p.text == "status*"));
+        assert!(rules.confirm.iter().any(|p| p.text == "commit*"));
+        assert!(rules.confirm.iter().any(|p| p.text == "revert*"));
+        let rebase = rules.deny.iter().find(|p| p.text == "rebase*").unwrap();
+        assert_eq!(rebase.source.as_deref(), Some(Path::new("gg.toml")));
+        let status = rules.allow.iter().find(|p| p.text == "status*").unwrap();
+        assert_eq!(
+            status.source.as_deref(),
+            Some(Path::new("preset:read-only"))
+        );
+    }
+
+    #[test]
+    fn test_extends_file_and_option_override() {
+        let dir = std::env::temp_dir().join("gg_test_extends");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("base.toml"),
+            r#"
+[options]
+deny_by_default = false
+log_file = "/tmp/base.log"
+
+[git.rules]
+deny = ["push --force*"]
+"#,
+        )
+        .unwrap();
+        let project = dir.join("gg.toml");
+        fs::write(
+            &project,
+            r#"
+extends = ["base.toml"]
+
+[options]
+deny_by_default = true
+
+[git.rules]
+allow = ["status*"]
+"#,
+        )
+        .unwrap();
+
+        let config = resolve_file(&project).unwrap();
+        assert!(config.options.deny_by_default);
+        assert_eq!(config.options.log_file.as_deref(), Some("/tmp/base.log"));
+        assert_eq!(texts(&config.git.rules.deny), vec!["push --force*"]);
+        assert_eq!(texts(&config.git.rules.allow), vec!["status*"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extends_cycle_is_an_error() {
+        let dir = std::env::temp_dir().join("gg_test_extends_cycle");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.toml"), r#"extends = ["b.toml"]"#).unwrap();
+        fs::write(dir.join("b.toml"), r#"extends = ["a.toml"]"#).unwrap();
+
+        let err = resolve_file(&dir.join("a.toml")).unwrap_err();
+        assert!(matches!(err, ConfigError::Cycle(ref chain) if chain.len() == 3));
+        assert!(err.to_string().contains("a.toml -> "));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extends_errors() {
+        let content = r#"extends = ["preset:strict"]"#;
+        let err = Config::resolve(Path::new("gg.toml"), content, &mut Vec::new()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown preset `strict` in gg.toml (available: read-only")
+        );
+
+        let content = r#"extends = ["/nonexistent/base.toml"]"#;
+        let err = Config::resolve(Path::new("gg.toml"), content, &mut Vec::new()).unwrap_err();
+        assert!(matches!(err, ConfigError::Read { .. }));
+    }
+
     #[test]
     fn test_config_search_paths_includes_local() {
-        let paths = config_search_paths();
+        let paths = local_config_paths();
         assert!(paths.iter().any(|p| p.ends_with("gg.toml")));
     }
 }
@@ -0,0 +1,381 @@
+use crate::config::{self, ConfirmChannel, Options};
+use crate::detect::Tool;
+use crate::logger::Context;
+use crate::rules::Evaluation;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, Stdio};
+use std::time::{Duration, Instant};
+
+/// What a confirm channel is asked to approve
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub tool: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub repo_root: Option<String>,
+    pub rule: Option<String>,
+    pub reason: Option<String>,
+    pub pid: u32,
+}
+
+impl Request {
+    pub fn new(tool: Tool, args: &[String], eval: &Evaluation) -> Self {
+        let context = Context::current();
+        Self {
+            tool: tool.to_string(),
+            args: args.to_vec(),
+            cwd: context.cwd.clone(),
+            repo_root: context.repo_root.clone(),
+            rule: eval.matched.as_ref().map(|m| m.to_string()),
+            reason: eval.matched.as_ref().and_then(|m| m.reason.clone()),
+            pid: std::process::id(),
+        }
+    }
+
+    fn command(&self) -> String {
+        format!("{} {}", self.tool, self.args.join(" "))
+    }
+}
+
+/// Ask on the first available channel in `confirm_via`. `Err` says why none
+/// of them could be used.
+pub fn ask(request: &Request, options: &Options) -> Answer {
+    let mut unavailable = Vec::new();
+    for &channel in &options.confirm_via {
+        let answer = match channel {
+            ConfirmChannel::Stdin => stdin(request),
+            ConfirmChannel::Tty => tty(request),
+            ConfirmChannel::Socket => socket(request, options),
+            ConfirmChannel::Command => command(request, options),
+        };
+        match answer {
+            Ok(approved) => return Ok(approved),
+            Err(why) => unavailable.push(why),
+        }
+    }
+    Err(unavailable.join("; "))
+}
+
+/// `Err` explains why the channel can't be used; `Ok` is the answer
+pub type Answer = Result<bool, String>;
+
+fn stdin(request: &Request) -> Answer {
+    if !io::stdin().is_terminal() {
+        return Err("stdin is not a terminal".to_string());
+    }
+    let mut stderr = io::stderr();
+    Ok(prompt(request, &mut io::stdin().lock(), &mut stderr))
+}
+
+#[cfg(unix)]
+fn tty(request: &Request) -> Answer {
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|e| format!("cannot open /dev/tty ({})", e))?;
+    let mut reader = io::BufReader::new(tty.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = tty;
+    Ok(prompt(request, &mut reader, &mut writer))
+}
+
+#[cfg(not(unix))]
+fn tty(_request: &Request) -> Answer {
+    Err("the tty channel needs /dev/tty".to_string())
+}
+
+fn prompt(request: &Request, input: &mut impl BufRead, out: &mut impl Write) -> bool {
+    let _ = write!(
+        out,
+        "[gg] confirm: `{}` — proceed? [y/N] ",
+        request.command()
+    );
+    let _ = out.flush();
+    let mut answer = String::new();
+    input.read_line(&mut answer).is_ok() && is_yes(&answer)
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `confirm_socket`, or `confirm.sock` in the data dir
+pub fn socket_path(options: &Options) -> Option<PathBuf> {
+    match &options.confirm_socket {
+        Some(path) => Some(PathBuf::from(path)),
+        None => config::data_dir().map(|dir| dir.join("confirm.sock")),
+    }
+}
+
+/// Send the request as one JSON line; the server answers `yes` or `no`
+#[cfg(unix)]
+fn socket(request: &Request, options: &Options) -> Answer {
+    use std::os::unix::net::UnixStream;
+
+    let path = socket_path(options).ok_or("no confirm socket path")?;
+    let stream = UnixStream::connect(&path)
+        .map_err(|e| format!("no `gg confirm serve` at {} ({})", path.display(), e))?;
+    let timeout = Duration::from_secs(options.confirm_timeout_secs);
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| e.to_string())?;
+
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    (&stream)
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("confirm socket: {}", e))?;
+    eprintln!(
+        "[gg] waiting for approval from `gg confirm serve` ({}s)...",
+        options.confirm_timeout_secs
+    );
+    let mut answer = String::new();
+    match io::BufReader::new(&stream).read_line(&mut answer) {
+        Ok(_) => Ok(is_yes(&answer)),
+        // Nobody answered in time: that's a no, not a missing channel
+        Err(_) => Ok(false),
+    }
+}
+
+#[cfg(not(unix))]
+fn socket(_request: &Request, _options: &Options) -> Answer {
+    Err("the socket channel needs Unix sockets".to_string())
+}
+
+/// Run `confirm_command` with `{command}` filled in; exit 0 approves
+fn command(request: &Request, options: &Options) -> Answer {
+    let (program, rest) = options
+        .confirm_command
+        .split_first()
+        .ok_or("confirm_command is not set")?;
+    let text = request.command();
+    let args: Vec<String> = rest.iter().map(|a| a.replace("{command}", &text)).collect();
+    let mut child = Command::new(program)
+        .args(&args)
+        .env("GG_CONFIRM_TOOL", &request.tool)
+        .env("GG_CONFIRM_COMMAND", &text)
+        .env("GG_CONFIRM_REASON", request.reason.as_deref().unwrap_or(""))
+        .env("GG_CONFIRM_CWD", request.cwd.as_deref().unwrap_or(""))
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run confirm_command `{}` ({})", program, e))?;
+
+    let deadline = Instant::now() + Duration::from_secs(options.confirm_timeout_secs);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status.success()),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// `gg confirm serve`: answer confirmation requests from this terminal
+pub fn serve(options: &Options) -> ExitCode {
+    // Answers come from stdin; piped in, an agent could approve itself
+    if !io::stdin().is_terminal() {
+        eprintln!("[gg] gg confirm serve must be run from a terminal");
+        return ExitCode::FAILURE;
+    }
+    let Some(path) = socket_path(options) else {
+        eprintln!("[gg] could not determine confirm socket path");
+        return ExitCode::FAILURE;
+    };
+    match listen(&path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("[gg] confirm serve: {}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another `gg confirm serve` is already running",
+        ));
+    }
+    // A socket file left behind by a server that's gone
+    let _ = std::fs::remove_file(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    eprintln!(
+        "[gg] answering confirmation requests on {} (Ctrl-C to stop)",
+        path.display()
+    );
+
+    let stdin = io::stdin();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("[gg] confirm serve: {}", e);
+                continue;
+            }
+        };
+        let mut line = String::new();
+        if io::BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let Ok(request) = serde_json::from_str::<Request>(&line) else {
+            eprintln!("[gg] confirm serve: ignoring malformed request");
+            continue;
+        };
+
+        eprintln!();
+        eprintln!(
+            "[gg] pid {} wants to run `{}`",
+            request.pid,
+            request.command()
+        );
+        if let Some(dir) = request.repo_root.as_ref().or(request.cwd.as_ref()) {
+            eprintln!("[gg]   in {}", dir);
+        }
+        if let Some(rule) = &request.rule {
+            eprintln!("[gg]   rule {}", rule);
+        }
+        if let Some(reason) = &request.reason {
+            eprintln!("[gg]   reason: {}", reason);
+        }
+        let approved = prompt(&request, &mut stdin.lock(), &mut io::stderr());
+        let answer = if approved { "yes\n" } else { "no\n" };
+        if (&stream).write_all(answer.as_bytes()).is_err() {
+            eprintln!("[gg] the request timed out before it was answered");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen(_path: &std::path::Path) -> io::Result<()> {
+    Err(io::Error::other("`gg confirm serve` needs Unix sockets"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> Request {
+        Request {
+            tool: "git".to_string(),
+            args: vec!["push".to_string(), "origin".to_string()],
+            cwd: Some("/src/app".to_string()),
+            repo_root: None,
+            rule: None,
+            reason: Some("shared branch".to_string()),
+            pid: 1,
+        }
+    }
+
+    fn options(via: ConfirmChannel) -> Options {
+        Options {
+            confirm_via: vec![via],
+            confirm_timeout_secs: 1,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_prompt_reads_answer() {
+        let mut out = Vec::new();
+        assert!(prompt(&request(), &mut "yes\n".as_bytes(), &mut out));
+        assert!(!prompt(&request(), &mut "\n".as_bytes(), &mut out));
+        assert!(!prompt(&request(), &mut "".as_bytes(), &mut out));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("[gg] confirm: `git push origin` — proceed? [y/N] "));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_channel() {
+        let mut options = options(ConfirmChannel::Command);
+        assert_eq!(
+            command(&request(), &options),
+            Err("confirm_command is not set".to_string())
+        );
+
+        options.confirm_command = [
+            "sh",
+            "-c",
+            "test \"$1\" = 'git push origin'",
+            "sh",
+            "{command}",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(command(&request(), &options), Ok(true));
+
+        options.confirm_command = ["sh", "-c", "test \"$GG_CONFIRM_REASON\" = nope"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(command(&request(), &options), Ok(false));
+
+        // Nobody answers: denied once the timeout passes
+        options.confirm_command = ["sleep", "5"].map(String::from).to_vec();
+        let start = Instant::now();
+        assert_eq!(command(&request(), &options), Ok(false));
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_channel() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = std::env::temp_dir().join("gg_test_confirm_socket");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("confirm.sock");
+        let mut options = options(ConfirmChannel::Socket);
+        options.confirm_socket = Some(path.to_str().unwrap().to_string());
+
+        assert!(
+            socket(&request(), &options)
+                .unwrap_err()
+                .contains("no `gg confirm serve`")
+        );
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            io::BufReader::new(&stream).read_line(&mut line).unwrap();
+            (&stream).write_all(b"yes\n").unwrap();
+            serde_json::from_str::<Request>(&line).unwrap()
+        });
+        assert_eq!(socket(&request(), &options), Ok(true));
+        assert_eq!(server.join().unwrap().args, ["push", "origin"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_falls_through_unavailable_channels() {
+        let mut options = options(ConfirmChannel::Command);
+        options.confirm_via = vec![ConfirmChannel::Command, ConfirmChannel::Command];
+        assert_eq!(
+            ask(&request(), &options),
+            Err("confirm_command is not set; confirm_command is not set".to_string())
+        );
+
+        options.confirm_command = vec!["true".to_string()];
+        options.confirm_via = vec![ConfirmChannel::Socket, ConfirmChannel::Command];
+        options.confirm_socket = Some("/nonexistent/gg/confirm.sock".to_string());
+        assert_eq!(ask(&request(), &options), Ok(true));
+    }
+}
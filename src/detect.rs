@@ -1,7 +1,9 @@
 use crate::config::{Config, Priority};
 use crate::rules;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Tool {
     Git,
     Gh,
@@ -16,84 +18,127 @@ impl std::fmt::Display for Tool {
     }
 }
 
+impl Tool {
+    /// The real binary to run: `$GG_GIT_PATH` / `$GG_GH_PATH`, else `git` / `gh`
+    pub fn binary(self) -> String {
+        let (var, default) = match self {
+            Tool::Git => ("GG_GIT_PATH", "git"),
+            Tool::Gh => ("GG_GH_PATH", "gh"),
+        };
+        std::env::var(var).unwrap_or_else(|_| default.to_string())
+    }
+}
+
+/// How `detect` arrived at its answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Via {
+    /// Only one tool's rules matched
+    Rules,
+    /// Both tools' rules matched; `options.priority` broke the tie
+    Priority(Priority),
+    /// No rules matched; the subcommand is on a known list (or on neither/both)
+    Subcommand,
+}
+
+impl std::fmt::Display for Via {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Via::Rules => write!(f, "only this tool's rules match"),
+            Via::Priority(p) => write!(f, "both git and gh rules match, priority = \"{}\"", p),
+            Via::Subcommand => write!(f, "no rules match, known subcommand list"),
+        }
+    }
+}
+
 /// Detect whether args belong to git or gh.
 /// 1. Check config rules for matches
 /// 2. If both match, use priority setting
 /// 3. If neither matches, fall back to known subcommand lists
 pub fn detect(config: &Config, args: &[String]) -> Option<Tool> {
-    let git_match = rules::has_any_match(&config.git.rules, args);
-    let gh_match = rules::has_any_match(&config.gh.rules, args);
+    detect_traced(config, args).0
+}
+
+/// Like `detect`, also reporting which step decided
+pub fn detect_traced(config: &Config, args: &[String]) -> (Option<Tool>, Via) {
+    let git_match = rules::has_any_match(Tool::Git, &config.git.rules, args);
+    let gh_match = rules::has_any_match(Tool::Gh, &config.gh.rules, args);
 
     match (git_match, gh_match) {
-        (true, false) => Some(Tool::Git),
-        (false, true) => Some(Tool::Gh),
-        (true, true) => Some(match config.options.priority {
-            Priority::Git => Tool::Git,
-            Priority::Gh => Tool::Gh,
-        }),
-        (false, false) => detect_by_subcommand(args),
+        (true, false) => (Some(Tool::Git), Via::Rules),
+        (false, true) => (Some(Tool::Gh), Via::Rules),
+        (true, true) => {
+            let priority = config.options.priority;
+            let tool = match priority {
+                Priority::Git => Tool::Git,
+                Priority::Gh => Tool::Gh,
+            };
+            (Some(tool), Via::Priority(priority))
+        }
+        (false, false) => (detect_by_subcommand(args), Via::Subcommand),
     }
 }
 
+/// Subcommands that are only git
+pub const GIT_COMMANDS: &[&str] = &[
+    "add",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "diff",
+    "fetch",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reflog",
+    "remote",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "submodule",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+/// Subcommands that are only gh
+pub const GH_COMMANDS: &[&str] = &[
+    "api",
+    "auth",
+    "cache",
+    "codespace",
+    "extension",
+    "gist",
+    "gpg-key",
+    "issue",
+    "label",
+    "pr",
+    "project",
+    "release",
+    "repo",
+    "ruleset",
+    "run",
+    "search",
+    "secret",
+    "ssh-key",
+    "variable",
+];
+
 fn detect_by_subcommand(args: &[String]) -> Option<Tool> {
     let sub = args.first().map(|s| s.as_str())?;
 
-    const GIT_COMMANDS: &[&str] = &[
-        "add",
-        "bisect",
-        "blame",
-        "branch",
-        "checkout",
-        "cherry-pick",
-        "clean",
-        "clone",
-        "commit",
-        "config",
-        "diff",
-        "fetch",
-        "init",
-        "log",
-        "merge",
-        "mv",
-        "pull",
-        "push",
-        "rebase",
-        "reflog",
-        "remote",
-        "reset",
-        "restore",
-        "revert",
-        "rm",
-        "show",
-        "stash",
-        "submodule",
-        "switch",
-        "tag",
-        "worktree",
-    ];
-
-    const GH_COMMANDS: &[&str] = &[
-        "api",
-        "auth",
-        "cache",
-        "codespace",
-        "extension",
-        "gist",
-        "gpg-key",
-        "issue",
-        "label",
-        "pr",
-        "project",
-        "release",
-        "repo",
-        "ruleset",
-        "run",
-        "search",
-        "secret",
-        "ssh-key",
-        "variable",
-    ];
-
     let is_git = GIT_COMMANDS.contains(&sub);
     let is_gh = GH_COMMANDS.contains(&sub);
 
@@ -117,22 +162,25 @@ mod tests {
         Config {
             git: ToolConfig {
                 rules: Rules {
-                    allow: vec!["status".to_string(), "log*".to_string()],
-                    confirm: vec!["push".to_string()],
-                    deny: vec!["push --force*".to_string()],
+                    allow: vec!["status".into(), "log*".into()],
+                    confirm: vec!["push".into()],
+                    deny: vec!["push --force*".into()],
+                    ..Rules::default()
                 },
             },
             gh: ToolConfig {
                 rules: Rules {
-                    allow: vec!["pr list*".to_string(), "status".to_string()],
+                    allow: vec!["pr list*".into(), "status".into()],
                     confirm: vec![],
-                    deny: vec!["pr merge*".to_string()],
+                    deny: vec!["pr merge*".into()],
+                    ..Rules::default()
                 },
             },
             options: Options {
                 priority: Priority::Git,
                 ..Options::default()
             },
+            ..Config::default()
         }
     }
 
@@ -173,6 +221,23 @@ mod tests {
         assert_eq!(detect(&config, &args("issue list")), Some(Tool::Gh));
     }
 
+    #[test]
+    fn test_detect_traced_via() {
+        let config = config_with_rules();
+        assert_eq!(
+            detect_traced(&config, &args("pr list")),
+            (Some(Tool::Gh), Via::Rules)
+        );
+        assert_eq!(
+            detect_traced(&config, &args("status")),
+            (Some(Tool::Git), Via::Priority(Priority::Git))
+        );
+        assert_eq!(
+            detect_traced(&config, &args("issue create")),
+            (Some(Tool::Gh), Via::Subcommand)
+        );
+    }
+
     #[test]
     fn test_detect_unknown_returns_none() {
         let config = empty_config();
@@ -0,0 +1,84 @@
+use crate::argv::CommandArgs;
+use crate::config::Config;
+use crate::detect::{self, Tool};
+use crate::grants;
+use crate::repo::{self, Repo};
+use crate::rules;
+use std::process::ExitCode;
+
+/// Print how gg would handle `args` — tool detection, every rule tested and
+/// the final decision — without executing anything.
+pub fn run(config: &Config, forced_tool: Option<Tool>, args: &[String]) -> ExitCode {
+    eprintln!("[gg] explain: {}", args.join(" "));
+
+    let tool = match forced_tool {
+        Some(tool) => {
+            eprintln!("tool: {} (forced with --{})", tool, tool);
+            tool
+        }
+        None => match detect::detect_traced(config, args) {
+            (Some(tool), via) => {
+                eprintln!("tool: {} ({})", tool, via);
+                tool
+            }
+            (None, via) => {
+                eprintln!("tool: undetermined ({}, on neither or both)", via);
+                eprintln!("decision: BLOCKED (exit 78, use --git or --gh)");
+                return ExitCode::SUCCESS;
+            }
+        },
+    };
+
+    if tool == Tool::Git {
+        let parsed = CommandArgs::parse(tool, args);
+        eprintln!("normalized: {}", parsed.normalized().join(" "));
+        let repo = Repo::for_command(tool, &parsed);
+        match repo.remote_urls() {
+            Some(urls) => {
+                for url in urls {
+                    eprintln!("remote: {} ({})", repo::normalize_url(url), url);
+                }
+            }
+            None => eprintln!("remote: unresolved"),
+        }
+        for spec in repo.push_specs() {
+            match &spec.src {
+                Some(src) => eprintln!("push: {} {} (from {})", spec.label(), spec.dst, src),
+                None => eprintln!("push: {} {}", spec.label(), spec.dst),
+            }
+        }
+    }
+
+    let tool_rules = match tool {
+        Tool::Git => &config.git.rules,
+        Tool::Gh => &config.gh.rules,
+    };
+    let grants = repo::scope(tool, &CommandArgs::parse(tool, args))
+        .map(|scope| grants::active(tool, &scope))
+        .unwrap_or_default();
+    let (eval, trace) = rules::evaluate_traced(
+        tool,
+        tool_rules,
+        &grants,
+        args,
+        config.options.deny_by_default,
+    );
+
+    eprintln!("rules tested (deny → confirm → allow, then grants):");
+    if trace.is_empty() {
+        eprintln!("  (none)");
+    }
+    for step in &trace {
+        let mark = if step.matched { "MATCH" } else { "-" };
+        eprintln!("  {:<5} {}", mark, step.rule);
+    }
+
+    if eval.matched.is_none() {
+        eprintln!(
+            "no rule matched (deny_by_default={})",
+            config.options.deny_by_default
+        );
+    }
+    eprintln!("decision: {}", eval.decision);
+    ExitCode::SUCCESS
+}
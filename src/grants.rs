@@ -0,0 +1,473 @@
+use crate::audit;
+use crate::config::{self, Config};
+use crate::detect::{self, Tool};
+use crate::logger::{self, Context, Event, Record};
+use crate::rotate::{self, Lock};
+use crate::rules::{Decision, Evaluation, Matched, RuleList};
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: gg grant [--git|--gh] <pattern...> [--for 1h] [--uses N] [--any-repo]
+       gg grant list
+       gg grant revoke <id>|--all";
+
+/// Allow commands matching `pattern` for a while, without editing config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grant {
+    pub id: String,
+    pub tool: Tool,
+    pub pattern: String,
+    /// `None` for every repo
+    pub repo: Option<String>,
+    pub created: i64,
+    pub expires: i64,
+    /// `None` for unlimited
+    pub uses_left: Option<u32>,
+}
+
+impl Grant {
+    fn live(&self, now: i64) -> bool {
+        now < self.expires && self.uses_left != Some(0)
+    }
+
+    fn applies(&self, tool: Tool, repo: &str, now: i64) -> bool {
+        self.tool == tool && self.live(now) && self.repo.as_deref().is_none_or(|r| r == repo)
+    }
+
+    /// `until 2026-03-01 13:00, 3 uses left, in /src/app`
+    pub fn scope(&self) -> String {
+        let until = Local
+            .timestamp_opt(self.expires, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut scope = format!("until {}", until);
+        if let Some(uses) = self.uses_left {
+            scope.push_str(&format!(
+                ", {} use{} left",
+                uses,
+                if uses == 1 { "" } else { "s" }
+            ));
+        }
+        match &self.repo {
+            Some(repo) => scope.push_str(&format!(", in {}", repo)),
+            None => scope.push_str(", in any repo"),
+        }
+        scope
+    }
+
+    pub fn to_matched(&self, index: usize) -> Matched {
+        Matched {
+            list: RuleList::Grant,
+            index,
+            pattern: self.pattern.clone(),
+            source: None,
+            id: Some(self.id.clone()),
+            reason: Some(self.scope()),
+            severity: None,
+            suggest: None,
+        }
+    }
+}
+
+/// `~/.local/share/gg/grants.json`
+fn default_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("grants.json"))
+}
+
+fn load(path: &Path) -> Vec<Grant> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Replace the file in one rename so readers never see half of it
+fn save(path: &Path, grants: &[Grant]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(grants).map_err(io::Error::other)?;
+    rotate::write_atomic(path, |file| file.write_all(json.as_bytes()))
+}
+
+/// Change the stored grants under the lock, dropping dead ones on the way
+fn update<T>(path: &Path, change: impl FnOnce(&mut Vec<Grant>) -> T) -> io::Result<T> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _lock = Lock::exclusive(path)?;
+    let now = Utc::now().timestamp();
+    let mut grants = load(path);
+    grants.retain(|g| g.live(now));
+    let result = change(&mut grants);
+    save(path, &grants)?;
+    Ok(result)
+}
+
+/// Grants that apply to `tool` in `repo` right now
+pub fn active(tool: Tool, repo: &str) -> Vec<Grant> {
+    default_path().map_or_else(Vec::new, |path| active_in(&path, tool, repo))
+}
+
+fn active_in(path: &Path, tool: Tool, repo: &str) -> Vec<Grant> {
+    let now = Utc::now().timestamp();
+    load(path)
+        .into_iter()
+        .filter(|g| g.applies(tool, repo, now))
+        .collect()
+}
+
+/// Count one use of grant `id`. False if it ran out, expired or was revoked
+/// since it was read, in which case it must not be relied on.
+pub fn spend(id: &str) -> bool {
+    default_path().is_some_and(|path| spend_in(&path, id))
+}
+
+fn spend_in(path: &Path, id: &str) -> bool {
+    update(path, |grants| {
+        let Some(grant) = grants.iter_mut().find(|g| g.id == id) else {
+            return false;
+        };
+        if let Some(uses) = &mut grant.uses_left {
+            *uses -= 1;
+        }
+        true
+    })
+    .unwrap_or(false)
+}
+
+/// `gg grant ...`
+pub fn run(config: &Config, args: &[String]) -> ExitCode {
+    let Some(path) = default_path() else {
+        eprintln!("[gg] could not determine the grants file");
+        return ExitCode::FAILURE;
+    };
+    match args.first().map(String::as_str) {
+        None => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+        Some("list") => {
+            let now = Utc::now().timestamp();
+            let grants: Vec<Grant> = load(&path).into_iter().filter(|g| g.live(now)).collect();
+            if grants.is_empty() {
+                println!("no active grants");
+            }
+            for grant in grants {
+                println!(
+                    "{}  {} {}  ({})",
+                    grant.id,
+                    grant.tool,
+                    grant.pattern,
+                    grant.scope()
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Some("revoke") => revoke(config, &path, &args[1..]),
+        Some(_) => grant(config, &path, args),
+    }
+}
+
+fn revoke(config: &Config, path: &Path, args: &[String]) -> ExitCode {
+    let which = match args {
+        [which] => which.as_str(),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let removed = update(path, |grants| {
+        let (removed, kept) = grants
+            .drain(..)
+            .partition(|g| which == "--all" || g.id == which);
+        *grants = kept;
+        removed
+    });
+    match removed {
+        Ok(removed) if removed.is_empty() => {
+            eprintln!("[gg] no active grant `{}`", which);
+            ExitCode::FAILURE
+        }
+        Ok(removed) => {
+            for grant in &removed {
+                log(config, Event::Revoke, grant);
+                eprintln!(
+                    "[gg] revoked {}: {} {}",
+                    grant.id, grant.tool, grant.pattern
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("[gg] cannot update {}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn grant(config: &Config, path: &Path, args: &[String]) -> ExitCode {
+    // Otherwise an agent could grant itself anything
+    if !io::stdin().is_terminal() {
+        eprintln!("[gg] gg grant must be run from a terminal");
+        return ExitCode::FAILURE;
+    }
+    let request = match Request::parse(args) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("[gg] {}", e);
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tool = match request
+        .tool
+        .or_else(|| detect::detect(config, &request.words))
+    {
+        Some(tool) => tool,
+        None => {
+            eprintln!(
+                "[gg] cannot tell if `{}` is git or gh; use --git or --gh",
+                request.words.join(" ")
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let pattern = request.words.join(" ");
+    let mut grant = Grant {
+        id: String::new(),
+        tool,
+        repo: (!request.any_repo).then(|| Context::current().repo_or_cwd()),
+        created: now,
+        expires: now + request.duration.num_seconds(),
+        uses_left: request.uses,
+        pattern,
+    };
+    grant.id = new_id(&grant);
+    if let Err(e) = update(path, |grants| grants.push(grant.clone())) {
+        eprintln!("[gg] cannot update {}: {}", path.display(), e);
+        return ExitCode::FAILURE;
+    }
+    log(config, Event::Grant, &grant);
+    eprintln!(
+        "[gg] granted {}: {} {} ({})",
+        grant.id,
+        grant.tool,
+        grant.pattern,
+        grant.scope()
+    );
+    ExitCode::SUCCESS
+}
+
+fn new_id(grant: &Grant) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(&grant.pattern);
+    hasher.update(grant.created.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("{:x}", hasher.finalize())[..6].to_string()
+}
+
+/// Grants and revocations go in the audit log like decisions do
+fn log(config: &Config, event: Event, grant: &Grant) {
+    if !config.options.log {
+        return;
+    }
+    let args: Vec<String> = grant.pattern.split_whitespace().map(String::from).collect();
+    let eval = Evaluation {
+        decision: Decision::Allow,
+        matched: Some(grant.to_matched(0)),
+    };
+    let record = Record {
+        event,
+        tool: grant.tool,
+        args: &args,
+        eval: &eval,
+        confirmed: None,
+        outcome: None,
+    };
+    logger::log_command(&record, &config.options);
+}
+
+/// Parsed `gg grant` arguments
+#[derive(Debug, PartialEq)]
+struct Request {
+    tool: Option<Tool>,
+    words: Vec<String>,
+    duration: chrono::Duration,
+    uses: Option<u32>,
+    any_repo: bool,
+}
+
+impl Request {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut request = Request {
+            tool: None,
+            words: Vec::new(),
+            duration: chrono::Duration::hours(1),
+            uses: None,
+            any_repo: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--git" => request.tool = Some(Tool::Git),
+                "--gh" => request.tool = Some(Tool::Gh),
+                "--any-repo" => request.any_repo = true,
+                "--for" => {
+                    let value = args.next().ok_or("--for needs a duration")?;
+                    request.duration = audit::parse_age(value)
+                        .filter(|d| d.num_seconds() > 0)
+                        .ok_or_else(|| format!("cannot parse duration `{}`", value))?;
+                }
+                "--uses" => {
+                    let value = args.next().ok_or("--uses needs a number")?;
+                    request.uses = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("cannot parse use count `{}`", value))?,
+                    );
+                }
+                _ => request
+                    .words
+                    .extend(arg.split_whitespace().map(String::from)),
+            }
+        }
+        if request.words.is_empty() {
+            return Err("no pattern given".to_string());
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("grants.json")
+    }
+
+    fn grant(id: &str, repo: Option<&str>, uses_left: Option<u32>) -> Grant {
+        let now = Utc::now().timestamp();
+        Grant {
+            id: id.to_string(),
+            tool: Tool::Git,
+            pattern: "push origin feat/*".to_string(),
+            repo: repo.map(String::from),
+            created: now,
+            expires: now + 3600,
+            uses_left,
+        }
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request = Request::parse(&args("push origin feat/* --for 30m --uses 3")).unwrap();
+        assert_eq!(request.words, args("push origin feat/*"));
+        assert_eq!(request.duration, chrono::Duration::minutes(30));
+        assert_eq!(request.uses, Some(3));
+        assert!(!request.any_repo);
+
+        // A quoted pattern is split like one typed as separate words
+        let quoted = vec![
+            "--gh".to_string(),
+            "pr merge*".to_string(),
+            "--any-repo".to_string(),
+        ];
+        let request = Request::parse(&quoted).unwrap();
+        assert_eq!(request.tool, Some(Tool::Gh));
+        assert_eq!(request.words, args("pr merge*"));
+        assert!(request.any_repo);
+
+        assert!(Request::parse(&args("push --for 0h")).is_err());
+        assert!(Request::parse(&args("push --for 99999999999999999d")).is_err());
+        assert!(Request::parse(&args("push --uses 0")).is_err());
+        assert!(Request::parse(&args("--for 1h")).is_err());
+    }
+
+    #[test]
+    fn test_active_grants_are_scoped() {
+        let path = temp_path("gg_test_grants_scope");
+        let mut expired = grant("aaaaaa", None, None);
+        expired.expires = Utc::now().timestamp() - 1;
+        let grants = vec![
+            expired,
+            grant("bbbbbb", Some("/src/app"), None),
+            grant("cccccc", None, Some(0)),
+            grant("dddddd", None, None),
+        ];
+        save(&path, &grants).unwrap();
+
+        let ids = |tool, repo| -> Vec<String> {
+            active_in(&path, tool, repo)
+                .into_iter()
+                .map(|g| g.id)
+                .collect()
+        };
+        assert_eq!(ids(Tool::Git, "/src/app"), ["bbbbbb", "dddddd"]);
+        assert_eq!(ids(Tool::Git, "/src/other"), ["dddddd"]);
+        assert!(ids(Tool::Gh, "/src/app").is_empty());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_grant_log_line() {
+        let grant = grant("aaaaaa", Some("/src/app"), Some(3));
+        let args = args(&grant.pattern);
+        let eval = Evaluation {
+            decision: Decision::Allow,
+            matched: Some(grant.to_matched(0)),
+        };
+        let record = Record {
+            event: Event::Grant,
+            tool: Tool::Git,
+            args: &args,
+            eval: &eval,
+            confirmed: None,
+            outcome: None,
+        };
+        let line = logger::text_body(&record);
+        assert!(line.starts_with(
+            r#"GRANT | git push origin feat/* | rule grant[0] "push origin feat/*" (id: aaaaaa) | until "#
+        ));
+        assert!(line.ends_with(", 3 uses left, in /src/app"), "{}", line);
+    }
+
+    #[test]
+    fn test_spend_counts_down_uses() {
+        let path = temp_path("gg_test_grants_spend");
+        save(
+            &path,
+            &[grant("aaaaaa", None, Some(2)), grant("bbbbbb", None, None)],
+        )
+        .unwrap();
+
+        assert!(spend_in(&path, "aaaaaa"));
+        assert!(spend_in(&path, "aaaaaa"));
+        assert!(!spend_in(&path, "aaaaaa"));
+        assert!(spend_in(&path, "bbbbbb"));
+        assert!(!spend_in(&path, "zzzzzz"));
+        // The used-up grant is dropped from the file on the next write
+        let ids: Vec<String> = load(&path).into_iter().map(|g| g.id).collect();
+        assert_eq!(ids, ["bbbbbb"]);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,13 +1,29 @@
+mod approval;
+mod argv;
+mod audit;
+mod chain;
+mod check;
 mod config;
+mod confirm;
 mod detect;
+mod explain;
+mod grants;
 mod logger;
+mod redact;
+mod repo;
+mod report;
+mod rotate;
 mod rules;
+mod syslog;
+mod webhook;
 
-use config::Config;
+use config::{Config, Output};
 use detect::Tool;
-use rules::Decision;
-use std::io::{self, IsTerminal, Write};
-use std::process::{Command, ExitCode};
+use logger::{Event as LogEvent, Outcome, Record};
+use report::{Event, Report};
+use rules::{Decision, Evaluation, RuleList};
+use std::process::{Command, ExitCode, ExitStatus};
+use std::time::Instant;
 
 fn main() -> ExitCode {
     let raw_args: Vec<String> = std::env::args().skip(1).collect();
@@ -27,10 +43,51 @@ fn main() -> ExitCode {
             return ExitCode::SUCCESS;
         }
         Some("--dump-config") => {
-            let config = Config::load();
+            let config = match load_config() {
+                Ok(config) => config,
+                Err(code) => return code,
+            };
             eprintln!("{:#?}", config);
             return ExitCode::SUCCESS;
         }
+        Some("config") if raw_args.get(1).map(String::as_str) == Some("check") => {
+            return match load_config() {
+                Ok(config) => check::run(&config),
+                Err(code) => code,
+            };
+        }
+        Some("confirm") if raw_args.get(1).map(String::as_str) == Some("serve") => {
+            return match load_config() {
+                Ok(config) => confirm::serve(&config.options),
+                Err(code) => code,
+            };
+        }
+        Some("audit") => {
+            return match load_config() {
+                Ok(config) => audit::run(&config, &raw_args[1..]),
+                Err(code) => code,
+            };
+        }
+        Some("approve") => {
+            return approval::run(&raw_args[1..]);
+        }
+        Some("grant") => {
+            return match load_config() {
+                Ok(config) => grants::run(&config, &raw_args[1..]),
+                Err(code) => code,
+            };
+        }
+        Some("explain" | "--why") => {
+            let (forced_tool, args) = parse_tool_flag(&raw_args[1..]);
+            if args.is_empty() {
+                eprintln!("[gg] usage: gg explain [--git|--gh] <command...>");
+                return ExitCode::FAILURE;
+            }
+            return match load_config() {
+                Ok(config) => explain::run(&config, forced_tool, &args),
+                Err(code) => code,
+            };
+        }
         _ => {}
     }
 
@@ -41,22 +98,38 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    let config = Config::load();
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(code) => return code,
+    };
+
+    let output = Output::resolve(config.options.output);
 
     let tool = match forced_tool {
         Some(t) => t,
         None => match detect::detect(&config, &args) {
             Some(t) => t,
             None => {
-                eprintln!(
-                    "[gg] BLOCKED: cannot determine if `{}` is git or gh",
-                    args.join(" ")
-                );
-                eprintln!(
-                    "[gg] hint: use `gg --git {}` or `gg --gh {}`",
-                    args.join(" "),
-                    args.join(" ")
-                );
+                let command = args.join(" ");
+                let message = format!("cannot determine if `{}` is git or gh", command);
+                let suggestions = vec![
+                    format!("gg --git {}", command),
+                    format!("gg --gh {}", command),
+                ];
+                if output == Output::Json {
+                    let report = Report {
+                        suggestions,
+                        exit_code: Some(78),
+                        ..Report::new(Event::Undetected, &args, message)
+                    };
+                    eprintln!("{}", report.to_json());
+                } else {
+                    eprintln!("[gg] BLOCKED: {}", message);
+                    eprintln!(
+                        "[gg] hint: use `{}` or `{}`",
+                        suggestions[0], suggestions[1]
+                    );
+                }
                 return ExitCode::from(78);
             }
         },
@@ -67,38 +140,226 @@ fn main() -> ExitCode {
         Tool::Gh => &config.gh.rules,
     };
 
-    let decision = rules::evaluate(tool_rules, &args, config.options.deny_by_default);
-
-    if config.options.log {
-        logger::log_command(tool, &args, &decision, config.options.log_file.as_deref());
+    // Grants and approvals are for the repo the command targets, which `-C`,
+    // `-R` or `GH_REPO` can change; if that can't be worked out, none apply
+    let scope = repo::scope(tool, &argv::CommandArgs::parse(tool, &args));
+    let grants = scope
+        .as_deref()
+        .map(|scope| grants::active(tool, scope))
+        .unwrap_or_default();
+    let mut eval = rules::evaluate_granted(
+        tool,
+        tool_rules,
+        &grants,
+        &args,
+        config.options.deny_by_default,
+    );
+    // A grant is only good if its use can be counted; one that ran out in the
+    // meantime leaves the static rules to decide
+    if let Some(matched) = &eval.matched
+        && matched.list == RuleList::Grant
+        && !matched.id.as_deref().is_some_and(grants::spend)
+    {
+        eval = rules::evaluate(tool, tool_rules, &args, config.options.deny_by_default);
     }
 
-    match decision {
-        Decision::Allow => exec(tool, &args),
+    // The decision is logged before anything runs, the result once it's known
+    let log = |event: LogEvent, confirmed: Option<bool>, outcome: Option<Outcome>| {
+        if config.options.log {
+            let record = Record {
+                event,
+                tool,
+                args: &args,
+                eval: &eval,
+                confirmed,
+                outcome,
+            };
+            logger::log_command(&record, &config.options);
+        }
+    };
+    log(LogEvent::Decision, None, None);
+    webhook::notify(&config.options, tool, &args, &eval);
+
+    let command = format!("{} {}", tool, args.join(" "));
+    match eval.decision {
+        Decision::Allow => {
+            let (code, outcome) = exec(tool, &args);
+            log(LogEvent::Result, None, outcome);
+            code
+        }
         Decision::Confirm => {
-            if confirm_with_user(tool, &args) {
-                exec(tool, &args)
+            if output == Output::Text {
+                print_match(&eval);
+            }
+            // An earlier `gg approve` for this exact command counts as a yes
+            let approved = scope
+                .as_deref()
+                .and_then(|repo| approval::take(tool, &args, repo));
+            let confirmed = match &approved {
+                Some(_) => true,
+                None => {
+                    match confirm::ask(&confirm::Request::new(tool, &args, &eval), &config.options)
+                    {
+                        Ok(confirmed) => confirmed,
+                        Err(why) => {
+                            return pending(
+                                tool,
+                                &args,
+                                &eval,
+                                scope.as_deref(),
+                                &why,
+                                &config,
+                                output,
+                            );
+                        }
+                    }
+                }
+            };
+            let message = match (&approved, confirmed) {
+                (Some(id), _) => format!("`{}` approved with `gg approve {}`", command, id),
+                (None, true) => format!("`{}` confirmed by user", command),
+                (None, false) => format!("`{}` needs confirmation and was cancelled", command),
+            };
+            if output == Output::Json {
+                let report = Report {
+                    confirmed: Some(confirmed),
+                    exit_code: (!confirmed).then_some(1),
+                    ..blocked_report(Event::Confirm, tool, &args, &eval, message)
+                };
+                eprintln!("{}", report.to_json());
+            } else if approved.is_some() {
+                eprintln!("[gg] {}", message);
+            }
+            if confirmed {
+                let (code, outcome) = exec(tool, &args);
+                log(LogEvent::Result, Some(true), outcome);
+                code
             } else {
-                eprintln!("[gg] cancelled by user");
+                if output == Output::Text {
+                    eprintln!("[gg] cancelled by user");
+                }
+                log(LogEvent::Result, Some(false), None);
                 ExitCode::FAILURE
             }
         }
-        Decision::Deny => {
-            eprintln!(
-                "[gg] BLOCKED: `{} {}` is denied by policy",
-                tool,
-                args.join(" ")
-            );
+        Decision::Deny | Decision::DefaultDeny => {
+            let message = if eval.decision == Decision::Deny {
+                format!("`{}` is denied by policy", command)
+            } else {
+                format!("`{}` has no matching rule (deny_by_default=true)", command)
+            };
+            if output == Output::Json {
+                let report = Report {
+                    exit_code: Some(77),
+                    ..blocked_report(Event::Blocked, tool, &args, &eval, message)
+                };
+                eprintln!("{}", report.to_json());
+            } else {
+                eprintln!("[gg] BLOCKED: {}", message);
+                print_match(&eval);
+            }
             ExitCode::from(77)
         }
-        Decision::DefaultDeny => {
-            eprintln!(
-                "[gg] BLOCKED: `{} {}` has no matching rule (deny_by_default=true)",
-                tool,
-                args.join(" ")
-            );
-            ExitCode::from(77)
+    }
+}
+
+/// Nobody could be asked: leave a request for `gg approve` and exit 80 so the
+/// agent knows to retry once it's approved
+fn pending(
+    tool: Tool,
+    args: &[String],
+    eval: &Evaluation,
+    repo: Option<&str>,
+    why: &str,
+    config: &Config,
+    output: Output,
+) -> ExitCode {
+    let command = format!("{} {}", tool, args.join(" "));
+    let requested = match repo {
+        Some(repo) => approval::request(tool, args, repo, &config.options),
+        None => Err(std::io::Error::other(
+            "the repository it targets can't be worked out",
+        )),
+    };
+    let id = match requested {
+        Ok(id) => id,
+        Err(e) => {
+            if output == Output::Text {
+                eprintln!("[gg] confirmation required but {}, denying", why);
+            }
+            eprintln!("[gg] could not record approval request: {}", e);
+            return ExitCode::FAILURE;
         }
+    };
+    let message = format!(
+        "`{}` needs approval: run `gg approve {}` in a terminal",
+        command, id
+    );
+    if output == Output::Json {
+        let report = Report {
+            suggestions: vec![format!("gg approve {}", id)],
+            exit_code: Some(80),
+            ..blocked_report(Event::Pending, tool, args, eval, message)
+        };
+        eprintln!("{}", report.to_json());
+    } else {
+        eprintln!("[gg] confirmation required but {}", why);
+        eprintln!("[gg] PENDING: {}", message);
+        eprintln!("[gg] then retry the same command; it will be allowed once");
+    }
+    ExitCode::from(80)
+}
+
+fn blocked_report<'a>(
+    event: Event,
+    tool: Tool,
+    args: &'a [String],
+    eval: &'a Evaluation,
+    message: String,
+) -> Report<'a> {
+    Report {
+        tool: Some(tool),
+        decision: Some(eval.decision),
+        matched: eval.matched.as_ref(),
+        suggestions: suggestion(eval).into_iter().collect(),
+        ..Report::new(event, args, message)
+    }
+}
+
+/// The matched rule's `suggest`, as a full gg command line
+fn suggestion(eval: &Evaluation) -> Option<String> {
+    let suggest = eval.matched.as_ref()?.suggest.as_ref()?;
+    Some(format!("gg {}", suggest))
+}
+
+/// Load the config, failing closed (exit 79) if any config file is broken
+fn load_config() -> Result<Config, ExitCode> {
+    Config::load().map_err(|e| {
+        if Output::resolve(Output::Text) == Output::Json {
+            let report = Report {
+                exit_code: Some(79),
+                ..Report::new(Event::ConfigError, &[], e.to_string())
+            };
+            eprintln!("{}", report.to_json());
+        } else {
+            eprintln!("[gg] BLOCKED: {}", e);
+            eprintln!("[gg] hint: fix the config file, or set GG_NO_STRICT=1 to skip broken files");
+        }
+        ExitCode::from(79)
+    })
+}
+
+fn print_match(eval: &Evaluation) {
+    let Some(matched) = &eval.matched else { return };
+    eprintln!("[gg] matched rule: {}", matched);
+    if let Some(reason) = &matched.reason {
+        match matched.severity {
+            Some(severity) => eprintln!("[gg] reason: {} [{}]", reason, severity),
+            None => eprintln!("[gg] reason: {}", reason),
+        }
+    }
+    if let Some(suggestion) = suggestion(eval) {
+        eprintln!("[gg] try: {}", suggestion);
     }
 }
 
@@ -110,43 +371,41 @@ fn parse_tool_flag(args: &[String]) -> (Option<Tool>, Vec<String>) {
     }
 }
 
-fn exec(tool: Tool, args: &[String]) -> ExitCode {
-    let bin = match tool {
-        Tool::Git => std::env::var("GG_GIT_PATH").unwrap_or_else(|_| "git".to_string()),
-        Tool::Gh => std::env::var("GG_GH_PATH").unwrap_or_else(|_| "gh".to_string()),
-    };
+/// Run the real git/gh, returning gg's exit code and how the command finished
+fn exec(tool: Tool, args: &[String]) -> (ExitCode, Option<Outcome>) {
+    let bin = tool.binary();
+    let start = Instant::now();
 
     match Command::new(&bin).args(args).status() {
         Ok(status) => {
+            let outcome = Outcome {
+                exit_code: status.code(),
+                signal: signal(&status),
+                duration: start.elapsed(),
+            };
             let code = status.code().unwrap_or(1);
-            ExitCode::from(code.clamp(0, 255) as u8)
+            (ExitCode::from(code.clamp(0, 255) as u8), Some(outcome))
         }
         Err(e) => {
             eprintln!("[gg] failed to execute {}: {}", bin, e);
-            ExitCode::FAILURE
+            let outcome = Outcome {
+                duration: start.elapsed(),
+                ..Outcome::default()
+            };
+            (ExitCode::FAILURE, Some(outcome))
         }
     }
 }
 
-fn confirm_with_user(tool: Tool, args: &[String]) -> bool {
-    if !io::stdin().is_terminal() {
-        eprintln!("[gg] confirmation required but stdin is not a terminal, denying");
-        return false;
-    }
-
-    eprint!(
-        "[gg] confirm: `{} {}` — proceed? [y/N] ",
-        tool,
-        args.join(" ")
-    );
-    io::stderr().flush().ok();
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
 
-    let mut input = String::new();
-    if io::stdin().read_line(&mut input).is_ok() {
-        matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
-    } else {
-        false
-    }
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
 }
 
 fn print_usage() {
@@ -154,6 +413,14 @@ fn print_usage() {
         "gg - Git & GitHub CLI Guard v{}
 
 Usage: gg [--git|--gh] <command...>
+       gg explain [--git|--gh] <command...>
+       gg config check
+       gg audit verify
+       gg audit show|top|summary [filters]
+       gg confirm serve
+       gg approve [<id>]
+       gg grant [--git|--gh] <pattern...> [--for 1h] [--uses N] [--any-repo]
+       gg grant list|revoke <id>
 
 A safety proxy for git and gh that enforces command policies.
 Auto-detects whether a command is git or gh.
@@ -162,6 +429,8 @@ Options:
   --git          Force command as git
   --gh           Force command as gh
   --dump-config  Show loaded configuration and exit
+  --why          Same as `explain`: trace detection and rule evaluation
+                 for a command without running it
   -h, --help     Show this help message
   -V, --version  Show version
 
@@ -171,6 +440,13 @@ Examples:
   gg --git status              # force → git status
   gg --gh status               # force → gh status
   gg push --force origin main  # denied if configured
+  gg explain status            # why does this go to git or gh?
+  gg config check              # lint the loaded rules
+  gg audit verify              # check the audit log's hash chain
+  gg audit top --since 1d      # most-blocked commands in the last day
+  gg confirm serve             # answer confirm prompts for agents from here
+  gg approve 3f9c2a            # let a pending confirm command run once
+  gg grant 'push origin feat/*' --for 1h   # allow without asking for an hour
 
 Config search order:
   1. ./gg.toml
@@ -183,6 +459,8 @@ Exit codes:
   0     Success
   77    Command blocked by policy
   78    Could not determine git/gh (use --git or --gh)
+  79    Config file could not be read or parsed
+  80    Needs confirmation: approve with `gg approve <id>`, then retry
   other Passthrough from git/gh",
         env!("CARGO_PKG_VERSION")
     );
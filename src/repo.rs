@@ -0,0 +1,972 @@
+use crate::argv::CommandArgs;
+use crate::detect::Tool;
+use crate::logger::Context;
+use std::cell::OnceCell;
+use std::process::{Command, Stdio};
+
+/// Placeholder in branch conditions for the branch `origin/HEAD` points at
+pub const DEFAULT_BRANCH: &str = "${default_branch}";
+
+/// Global options that change which repository, or which config, git sees
+const REPO_GLOBALS: &[&str] = &["-C", "-c", "--git-dir", "--work-tree"];
+
+/// Global options that point git at another repository
+const LOCATION_GLOBALS: &[&str] = &["-C", "--git-dir", "--work-tree"];
+
+type Git = Box<dyn Fn(&[&str]) -> Option<String>>;
+
+/// The repository a command runs in, as far as rule conditions care. Each
+/// piece is read from git the first time a rule asks for it, so commands
+/// whose rules have no branch conditions never pay for it.
+pub struct Repo {
+    cmd: CommandArgs,
+    git: Git,
+    current_branch: OnceCell<Option<String>>,
+    default_branch: OnceCell<Option<String>>,
+    upstream: OnceCell<Option<String>>,
+    tracking: OnceCell<Vec<String>>,
+    push_specs: OnceCell<Vec<PushSpec>>,
+    remote_urls: OnceCell<Option<Vec<String>>>,
+}
+
+/// What a push does to one remote ref
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    Create,
+    FastForward,
+    Force,
+    Delete,
+}
+
+impl Update {
+    pub const ALL: [Update; 4] = [
+        Update::Create,
+        Update::FastForward,
+        Update::Force,
+        Update::Delete,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Update::Create => "create",
+            Update::FastForward => "fast-forward",
+            Update::Force => "force",
+            Update::Delete => "delete",
+        }
+    }
+}
+
+/// Kinds of ref a push update is labelled with, besides `ref` for the rest
+pub const REF_KINDS: [&str; 3] = ["branch", "tag", "ref"];
+
+/// One ref a `git push` would update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushSpec {
+    /// Local ref pushed; `None` when the remote ref is deleted
+    pub src: Option<String>,
+    /// Full remote ref, e.g. `refs/heads/main`
+    pub dst: String,
+    pub update: Update,
+}
+
+impl PushSpec {
+    /// The branch name, if `dst` is a branch
+    pub fn branch(&self) -> Option<&str> {
+        self.dst.strip_prefix("refs/heads/")
+    }
+
+    /// `branch`, `tag` or `ref`
+    pub fn kind(&self) -> &'static str {
+        if self.dst.starts_with("refs/heads/") {
+            "branch"
+        } else if self.dst.starts_with("refs/tags/") {
+            "tag"
+        } else {
+            "ref"
+        }
+    }
+
+    /// `<kind>:<update>`, e.g. `branch:force`, which `push_update`
+    /// conditions match against
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.kind(), self.update.name())
+    }
+}
+
+/// What approvals and grants for `cmd` are scoped to: the repo it targets.
+/// That's the working directory's repo unless `-C`, `--git-dir` or
+/// `--work-tree` point elsewhere; `None` if they do and git can't say where.
+/// A gh command sent elsewhere with `-R`/`--repo` or `GH_REPO` is scoped to
+/// the `OWNER/REPO` it names, which no repository-scoped grant is for.
+pub fn scope(tool: Tool, cmd: &CommandArgs) -> Option<String> {
+    if tool == Tool::Gh
+        && let Some(target) = gh_target(cmd)
+    {
+        return Some(target);
+    }
+    let elsewhere = tool == Tool::Git
+        && cmd
+            .globals
+            .iter()
+            .any(|o| LOCATION_GLOBALS.contains(&o.name.as_str()));
+    if !elsewhere {
+        return Some(Context::current().repo_or_cwd());
+    }
+    let repo = Repo::for_command(tool, cmd);
+    (repo.git)(&["rev-parse", "--show-toplevel"]).filter(|top| !top.is_empty())
+}
+
+/// The repository a gh command names instead of the working directory's;
+/// `-R` wins over `GH_REPO`, as in gh
+fn gh_target(cmd: &CommandArgs) -> Option<String> {
+    cmd.options
+        .iter()
+        .rev()
+        .find(|o| o.name == "-R" || o.name == "--repo")
+        .map(|o| o.value.clone().unwrap_or_default())
+        .or_else(|| std::env::var("GH_REPO").ok())
+        .filter(|target| !target.is_empty())
+}
+
+/// A `push_update` entry as a glob over labels: a bare `force` stands for
+/// `*:force`
+pub fn update_pattern(entry: &str) -> String {
+    if entry.contains(':') {
+        entry.to_string()
+    } else {
+        format!("*:{}", entry)
+    }
+}
+
+/// `host/path` for a remote URL, so `https://github.com/o/r.git`,
+/// `git@github.com:o/r` and `ssh://git@github.com:22/o/r` all read
+/// `github.com/o/r`. Local paths keep their path.
+pub fn normalize_url(url: &str) -> String {
+    let (host, path) = match url.split_once("://") {
+        Some(("file", path)) => ("", path),
+        Some((_, rest)) => rest.split_once('/').unwrap_or((rest, "")),
+        // scp-like `[user@]host:path`, unless the colon comes after a slash
+        None => match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') => (host, path),
+            _ => ("", url),
+        },
+    };
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host).to_lowercase();
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", host, path.trim_start_matches('/'))
+    }
+}
+
+/// How a `git push` treats its refspecs
+struct PushFlags {
+    remote: String,
+    force: bool,
+    delete: bool,
+    prune: bool,
+}
+
+impl Repo {
+    /// The repository `cmd` runs in; its `-C`, `-c`, `--git-dir` and
+    /// `--work-tree` options are passed on to git
+    pub fn for_command(tool: Tool, cmd: &CommandArgs) -> Self {
+        let mut prefix = Vec::new();
+        if tool == Tool::Git {
+            for opt in cmd
+                .globals
+                .iter()
+                .filter(|o| REPO_GLOBALS.contains(&o.name.as_str()))
+            {
+                match &opt.value {
+                    Some(value) if opt.name.starts_with("--") => {
+                        prefix.push(format!("{}={}", opt.name, value))
+                    }
+                    Some(value) => prefix.extend([opt.name.clone(), value.clone()]),
+                    None => {}
+                }
+            }
+        }
+        let git = move |args: &[&str]| {
+            let output = Command::new(Tool::Git.binary())
+                .args(&prefix)
+                .args(args)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        Self::with_git(cmd.clone(), git)
+    }
+
+    pub fn with_git(cmd: CommandArgs, git: impl Fn(&[&str]) -> Option<String> + 'static) -> Self {
+        Self {
+            cmd,
+            git: Box::new(git),
+            current_branch: OnceCell::new(),
+            default_branch: OnceCell::new(),
+            upstream: OnceCell::new(),
+            tracking: OnceCell::new(),
+            push_specs: OnceCell::new(),
+            remote_urls: OnceCell::new(),
+        }
+    }
+
+    fn config(&self, key: &str) -> Option<String> {
+        (self.git)(&["config", "--get", key]).filter(|v| !v.is_empty())
+    }
+
+    fn config_all(&self, key: &str) -> Vec<String> {
+        (self.git)(&["config", "--get-all", key])
+            .map(|out| out.lines().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    fn refs(&self, prefix: &str) -> Vec<String> {
+        (self.git)(&["for-each-ref", "--format=%(refname)", prefix])
+            .map(|out| out.lines().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// `None` on a detached HEAD or outside a repository
+    pub fn current_branch(&self) -> Option<&str> {
+        self.current_branch
+            .get_or_init(|| (self.git)(&["symbolic-ref", "--quiet", "--short", "HEAD"]))
+            .as_deref()
+    }
+
+    /// The branch `refs/remotes/origin/HEAD` points at, else
+    /// `init.defaultBranch`, else whichever of `main` and `master` exists
+    pub fn default_branch(&self) -> Option<&str> {
+        self.default_branch
+            .get_or_init(|| {
+                (self.git)(&["symbolic-ref", "--quiet", "refs/remotes/origin/HEAD"])
+                    .and_then(|head| head.strip_prefix("refs/remotes/origin/").map(String::from))
+                    .or_else(|| self.config("init.defaultBranch"))
+                    .or_else(|| {
+                        let local = self.refs("refs/heads/");
+                        ["main", "master"]
+                            .into_iter()
+                            .find(|b| local.contains(&format!("refs/heads/{}", b)))
+                            .map(String::from)
+                    })
+            })
+            .as_deref()
+    }
+
+    /// The current branch's upstream, as a branch name on its remote
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream
+            .get_or_init(|| {
+                let branch = self.current_branch()?;
+                let merge = self.config(&format!("branch.{}.merge", branch))?;
+                Some(
+                    merge
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(&merge)
+                        .to_string(),
+                )
+            })
+            .as_deref()
+    }
+
+    /// A condition glob with `${default_branch}` filled in. `None` if the
+    /// default branch is needed but none of its sources gives one, so the
+    /// glob matches nothing.
+    pub fn expand(&self, glob: &str) -> Option<String> {
+        if !glob.contains(DEFAULT_BRANCH) {
+            return Some(glob.to_string());
+        }
+        Some(glob.replace(DEFAULT_BRANCH, self.default_branch()?))
+    }
+
+    /// What a `git push` would update, from its refspecs, or, without any,
+    /// from `remote.<name>.push` and `push.default`
+    pub fn push_specs(&self) -> &[PushSpec] {
+        self.push_specs.get_or_init(|| {
+            if self.cmd.subcommand.as_deref() != Some("push") {
+                return Vec::new();
+            }
+            let has = |name: &str| self.cmd.options.iter().any(|o| o.name == name);
+            let mirror = has("--mirror");
+            let flags = PushFlags {
+                remote: self.push_remote(),
+                force: mirror || has("--force") || has("-f") || has("--force-with-lease"),
+                delete: has("--delete") || has("-d"),
+                prune: mirror || has("--prune"),
+            };
+
+            let mut refspecs: Vec<String> = if mirror {
+                vec!["refs/*:refs/*".to_string()]
+            } else if has("--all") || has("--branches") {
+                vec!["refs/heads/*:refs/heads/*".to_string()]
+            } else {
+                // The first operand is the remote, even with `--repo`
+                self.cmd.positionals.iter().skip(1).cloned().collect()
+            };
+            if has("--tags") {
+                refspecs.push("refs/tags/*:refs/tags/*".to_string());
+            }
+            if refspecs.is_empty() {
+                refspecs = self.config_all(&format!("remote.{}.push", flags.remote));
+            }
+            if refspecs.is_empty() {
+                return self.push_default(&flags);
+            }
+            refspecs
+                .iter()
+                .flat_map(|spec| self.resolve(spec, &flags))
+                .collect()
+        })
+    }
+
+    /// The remote named on the command line, else the configured push
+    /// remote. As in git, an operand wins over `--repo`.
+    pub fn push_remote(&self) -> String {
+        if let Some(remote) = self.cmd.positionals.first() {
+            return remote.clone();
+        }
+        if let Some(repo) = self.cmd.options.iter().find(|o| o.name == "--repo") {
+            return repo.value.clone().unwrap_or_default();
+        }
+        let branch = self.current_branch();
+        branch
+            .and_then(|b| self.config(&format!("branch.{}.pushRemote", b)))
+            .or_else(|| self.config("remote.pushDefault"))
+            .or_else(|| branch.and_then(|b| self.config(&format!("branch.{}.remote", b))))
+            .unwrap_or_else(|| "origin".to_string())
+    }
+
+    /// URLs a push, fetch or pull talks to, as given to git; empty for any
+    /// other command. A push to a remote with several push URLs goes to all.
+    /// `None` if a remote it names can't be resolved.
+    pub fn remote_urls(&self) -> Option<&[String]> {
+        self.remote_urls
+            .get_or_init(|| {
+                let (push, remotes) = match self.cmd.subcommand.as_deref() {
+                    Some("push") => (true, vec![self.push_remote()]),
+                    Some("fetch" | "pull") => (false, self.fetch_remotes()),
+                    _ => return Some(Vec::new()),
+                };
+                if remotes.is_empty() {
+                    return None;
+                }
+                let urls: Option<Vec<Vec<String>>> = remotes
+                    .iter()
+                    .map(|remote| self.url_of(remote, push))
+                    .collect();
+                urls.map(|urls| urls.concat())
+            })
+            .as_deref()
+    }
+
+    /// Remote URLs as `host/path`, the form `remote` conditions match
+    pub fn remotes(&self) -> Option<Vec<String>> {
+        Some(
+            self.remote_urls()?
+                .iter()
+                .map(|url| normalize_url(url))
+                .collect(),
+        )
+    }
+
+    fn fetch_remotes(&self) -> Vec<String> {
+        let has = |name: &str| self.cmd.options.iter().any(|o| o.name == name);
+        if has("--all") {
+            return (self.git)(&["remote"])
+                .map(|out| out.lines().map(String::from).collect())
+                .unwrap_or_default();
+        }
+        if has("--multiple") {
+            return self.cmd.positionals.clone();
+        }
+        if let Some(remote) = self.cmd.positionals.first() {
+            return vec![remote.clone()];
+        }
+        self.current_branch()
+            .and_then(|b| self.config(&format!("branch.{}.remote", b)))
+            .map_or_else(|| vec!["origin".to_string()], |r| vec![r])
+    }
+
+    /// A configured remote's URLs, with `pushurl` and `insteadOf` applied by
+    /// git; anything else that reads as a URL or path is rewritten here.
+    /// `None` for a bare name git doesn't know as a remote.
+    fn url_of(&self, remote: &str, push: bool) -> Option<Vec<String>> {
+        let mut args = vec!["remote", "get-url", "--all"];
+        if push {
+            args.push("--push");
+        }
+        args.push(remote);
+        match (self.git)(&args) {
+            Some(urls) => Some(urls.lines().map(String::from).collect()),
+            None if remote.contains([':', '/']) || remote.starts_with('.') => {
+                Some(vec![self.rewrite(remote, push)])
+            }
+            None => None,
+        }
+    }
+
+    /// Apply `url.<base>.insteadOf`, or for a push `pushInsteadOf` first; the
+    /// longest matching prefix wins, as in git
+    fn rewrite(&self, url: &str, push: bool) -> String {
+        let rules: Vec<(String, String, String)> =
+            (self.git)(&["config", "--get-regexp", r"^url\..*\.(push)?insteadof$"])
+                .map(|out| {
+                    out.lines()
+                        .filter_map(|line| {
+                            let (key, prefix) = line.split_once(' ')?;
+                            let (base, kind) = key.strip_prefix("url.")?.rsplit_once('.')?;
+                            Some((kind.to_lowercase(), base.to_string(), prefix.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+        let kinds: &[&str] = if push {
+            &["pushinsteadof", "insteadof"]
+        } else {
+            &["insteadof"]
+        };
+        for kind in kinds {
+            let best = rules
+                .iter()
+                .filter(|(k, _, prefix)| k == kind && url.starts_with(prefix.as_str()))
+                .max_by_key(|(_, _, prefix)| prefix.len());
+            if let Some((_, base, prefix)) = best {
+                return format!("{}{}", base, &url[prefix.len()..]);
+            }
+        }
+        url.to_string()
+    }
+
+    fn push_default(&self, flags: &PushFlags) -> Vec<PushSpec> {
+        let mode = self.config("push.default");
+        let Some(branch) = self.current_branch() else {
+            return Vec::new();
+        };
+        let local = format!("refs/heads/{}", branch);
+        let to = |dst: String| vec![self.spec(Some(local.clone()), dst, flags.force, flags)];
+        match mode.as_deref().unwrap_or("simple") {
+            "nothing" => Vec::new(),
+            "matching" => self.resolve(":", flags),
+            "upstream" | "tracking" => {
+                let tracked = self.config(&format!("branch.{}.remote", branch));
+                match self.upstream() {
+                    Some(upstream) if tracked.as_deref() == Some(flags.remote.as_str()) => {
+                        to(format!("refs/heads/{}", upstream))
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            // `simple` and `current` both push to the same name
+            _ => to(local.clone()),
+        }
+    }
+
+    /// Remote branches as of the last fetch, as full refs on the remote
+    fn tracking(&self, remote: &str) -> &[String] {
+        self.tracking.get_or_init(|| {
+            let prefix = format!("refs/remotes/{}/", remote);
+            self.refs(&prefix)
+                .into_iter()
+                .filter_map(|r| {
+                    let name = r.strip_prefix(&prefix)?;
+                    (name != "HEAD").then(|| format!("refs/heads/{}", name))
+                })
+                .collect()
+        })
+    }
+
+    /// Classify an update from what the last fetch saw of the remote. Tags
+    /// aren't tracked, and neither is anything on a remote that was never
+    /// fetched; for those a forced update is assumed to overwrite something,
+    /// and an unforced one can only create.
+    fn spec(&self, src: Option<String>, dst: String, force: bool, flags: &PushFlags) -> PushSpec {
+        let tracking = self.tracking(&flags.remote);
+        let exists = if dst.starts_with("refs/heads/") && !tracking.is_empty() {
+            tracking.contains(&dst)
+        } else {
+            force
+        };
+        let update = match (&src, exists, force) {
+            (None, _, _) => Update::Delete,
+            (Some(_), false, _) => Update::Create,
+            (Some(_), true, true) => Update::Force,
+            (Some(_), true, false) => Update::FastForward,
+        };
+        PushSpec { src, dst, update }
+    }
+
+    /// `[+]<src>[:<dst>]`, `:<dst>` (delete) or `:` (matching branches)
+    fn resolve(&self, spec: &str, flags: &PushFlags) -> Vec<PushSpec> {
+        // Negative refspecs only narrow what the others push
+        if spec.starts_with('^') {
+            return Vec::new();
+        }
+        let (spec, force) = match spec.strip_prefix('+') {
+            Some(spec) => (spec, true),
+            None => (spec, flags.force),
+        };
+        if spec == ":" {
+            let theirs = self.tracking(&flags.remote).to_vec();
+            return self
+                .refs("refs/heads/")
+                .into_iter()
+                .filter(|r| theirs.contains(r))
+                .map(|r| self.spec(Some(r.clone()), r, force, flags))
+                .collect();
+        }
+        let (src, dst) = match spec.split_once(':') {
+            Some((src, dst)) => (src, dst),
+            None => (spec, spec),
+        };
+        if flags.delete || src.is_empty() {
+            return vec![self.spec(None, self.qualify(dst, None), force, flags)];
+        }
+        if src.contains('*') {
+            return self.resolve_glob(src, dst, force, flags);
+        }
+        let src = match src {
+            "HEAD" | "@" => match self.current_branch() {
+                Some(branch) => format!("refs/heads/{}", branch),
+                None => "HEAD".to_string(),
+            },
+            _ => self.qualify_local(src),
+        };
+        let dst = if spec.contains(':') {
+            self.qualify(dst, Some(&src))
+        } else {
+            self.qualify(&src, Some(&src))
+        };
+        vec![self.spec(Some(src), dst, force, flags)]
+    }
+
+    /// `refs/heads/*:refs/heads/mirror/*` and the like. With `--prune`, remote
+    /// branches the pattern covers but that have no local source are deleted.
+    fn resolve_glob(&self, src: &str, dst: &str, force: bool, flags: &PushFlags) -> Vec<PushSpec> {
+        let (src_prefix, src_suffix) = src.split_once('*').unwrap_or((src, ""));
+        let src_prefix = self.qualify_local(src_prefix);
+        let local = self.refs(&src_prefix);
+        let mut specs: Vec<PushSpec> = local
+            .iter()
+            .filter_map(|r| {
+                let middle = r.strip_prefix(&src_prefix)?.strip_suffix(src_suffix)?;
+                let dst = self.qualify(&dst.replacen('*', middle, 1), Some(r));
+                Some(self.spec(Some(r.clone()), dst, force, flags))
+            })
+            .collect();
+        if flags.prune {
+            let (dst_prefix, dst_suffix) = dst.split_once('*').unwrap_or((dst, ""));
+            let stale: Vec<String> = self
+                .tracking(&flags.remote)
+                .iter()
+                .filter(|theirs| {
+                    theirs
+                        .strip_prefix(dst_prefix)
+                        .and_then(|r| r.strip_suffix(dst_suffix))
+                        .is_some_and(|middle| {
+                            let ours = format!("{}{}{}", src_prefix, middle, src_suffix);
+                            !local.contains(&ours)
+                        })
+                })
+                .cloned()
+                .collect();
+            specs.extend(
+                stale
+                    .into_iter()
+                    .map(|dst| self.spec(None, dst, force, flags)),
+            );
+        }
+        specs
+    }
+
+    /// A local ref name in full: a tag if a tag by that name exists, else a
+    /// branch
+    fn qualify_local(&self, name: &str) -> String {
+        if name.starts_with("refs/") {
+            return name.to_string();
+        }
+        let tag = format!("refs/tags/{}", name);
+        if (self.git)(&["show-ref", "--verify", "--quiet", &tag]).is_some() {
+            tag
+        } else {
+            format!("refs/heads/{}", name)
+        }
+    }
+
+    /// A remote ref name in full; short names take their kind from the source
+    fn qualify(&self, name: &str, src: Option<&str>) -> String {
+        if name.starts_with("refs/") {
+            return name.to_string();
+        }
+        let kind = match src {
+            Some(src) if src.starts_with("refs/tags/") => "refs/tags/",
+            _ => "refs/heads/",
+        };
+        format!("{}{}", kind, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A repo on `feature/login` tracking `origin/main`, with `main` as
+    /// origin's default branch
+    fn repo(cmd: &str, extra: &[(&str, &str)]) -> Repo {
+        let mut answers: HashMap<String, String> = [
+            ("symbolic-ref --quiet --short HEAD", "feature/login"),
+            ("symbolic-ref --quiet refs/remotes/origin/HEAD", "refs/remotes/origin/main"),
+            ("config --get branch.feature/login.remote", "origin"),
+            ("config --get branch.feature/login.merge", "refs/heads/main"),
+            (
+                "for-each-ref --format=%(refname) refs/heads/",
+                "refs/heads/feature/login\nrefs/heads/main\nrefs/heads/wip",
+            ),
+            (
+                "for-each-ref --format=%(refname) refs/remotes/origin/",
+                "refs/remotes/origin/HEAD\nrefs/remotes/origin/main\nrefs/remotes/origin/feature/login",
+            ),
+            ("show-ref --verify --quiet refs/tags/v1.0", ""),
+        ]
+        .into_iter()
+        .chain(extra.iter().copied())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        answers.retain(|_, v| v != "<none>");
+        let args: Vec<String> = cmd.split_whitespace().map(String::from).collect();
+        Repo::with_git(CommandArgs::parse(Tool::Git, &args), move |args| {
+            answers.get(&args.join(" ")).cloned()
+        })
+    }
+
+    fn targets(repo: &Repo) -> Vec<&str> {
+        repo.push_specs()
+            .iter()
+            .filter_map(PushSpec::branch)
+            .collect()
+    }
+
+    /// `<label> <dst>` for each update
+    fn updates(repo: &Repo) -> Vec<String> {
+        repo.push_specs()
+            .iter()
+            .map(|spec| format!("{} {}", spec.label(), spec.dst))
+            .collect()
+    }
+
+    #[test]
+    fn test_branches_and_placeholder() {
+        let repo = repo("status", &[]);
+        assert_eq!(repo.current_branch(), Some("feature/login"));
+        assert_eq!(repo.upstream(), Some("main"));
+        assert_eq!(repo.expand("${default_branch}").as_deref(), Some("main"));
+        assert_eq!(repo.expand("release/*").as_deref(), Some("release/*"));
+        assert!(targets(&repo).is_empty());
+
+        let detached = self::repo(
+            "status",
+            &[
+                ("symbolic-ref --quiet --short HEAD", "<none>"),
+                ("symbolic-ref --quiet refs/remotes/origin/HEAD", "<none>"),
+            ],
+        );
+        assert_eq!(detached.current_branch(), None);
+        assert_eq!(detached.upstream(), None);
+        assert_eq!(
+            detached.expand("${default_branch}").as_deref(),
+            Some("main")
+        );
+
+        // Without origin/HEAD: init.defaultBranch, then main or master
+        let no_head = ("symbolic-ref --quiet refs/remotes/origin/HEAD", "<none>");
+        let configured = self::repo(
+            "status",
+            &[no_head, ("config --get init.defaultBranch", "trunk")],
+        );
+        assert_eq!(configured.default_branch(), Some("trunk"));
+        let master = self::repo(
+            "status",
+            &[
+                no_head,
+                (
+                    "for-each-ref --format=%(refname) refs/heads/",
+                    "refs/heads/master\nrefs/heads/wip",
+                ),
+            ],
+        );
+        assert_eq!(master.default_branch(), Some("master"));
+        let neither = self::repo(
+            "status",
+            &[
+                no_head,
+                (
+                    "for-each-ref --format=%(refname) refs/heads/",
+                    "refs/heads/wip",
+                ),
+            ],
+        );
+        assert_eq!(neither.expand("${default_branch}"), None);
+    }
+
+    #[test]
+    fn test_push_refspecs() {
+        assert_eq!(targets(&repo("push origin main", &[])), ["main"]);
+        assert_eq!(
+            targets(&repo("push origin HEAD:release/2", &[])),
+            ["release/2"]
+        );
+        assert_eq!(targets(&repo("push -f origin +wip:main", &[])), ["main"]);
+        assert_eq!(targets(&repo("push origin :old", &[])), ["old"]);
+        assert_eq!(targets(&repo("push origin --delete old", &[])), ["old"]);
+        // An operand names the remote even with `--repo`, as in git
+        let repo_and_remote = repo("push --repo=origin upstream main", &[]);
+        assert_eq!(repo_and_remote.push_remote(), "upstream");
+        assert_eq!(targets(&repo_and_remote), ["main"]);
+        assert_eq!(repo("push --repo=fork", &[]).push_remote(), "fork");
+        assert_eq!(
+            targets(&repo("push origin refs/heads/*:refs/heads/mirror/*", &[])),
+            ["mirror/feature/login", "mirror/main", "mirror/wip"]
+        );
+        // Tags aren't branches
+        assert!(targets(&repo("push origin v1.0", &[])).is_empty());
+        assert_eq!(
+            targets(&repo("push --all origin", &[])),
+            ["feature/login", "main", "wip"]
+        );
+        assert_eq!(
+            targets(&repo("push origin :", &[])),
+            ["feature/login", "main"]
+        );
+    }
+
+    #[test]
+    fn test_push_defaults() {
+        // `simple` pushes the current branch to the same name
+        assert_eq!(targets(&repo("push", &[])), ["feature/login"]);
+        assert_eq!(targets(&repo("push origin", &[])), ["feature/login"]);
+        assert_eq!(
+            targets(&repo("push", &[("config --get push.default", "upstream")])),
+            ["main"]
+        );
+        assert!(
+            targets(&repo(
+                "push fork",
+                &[("config --get push.default", "upstream")]
+            ))
+            .is_empty()
+        );
+        assert!(targets(&repo("push", &[("config --get push.default", "nothing")])).is_empty());
+        assert_eq!(
+            targets(&repo("push", &[("config --get push.default", "matching")])),
+            ["feature/login", "main"]
+        );
+        // A configured refspec wins over push.default
+        assert_eq!(
+            targets(&repo(
+                "push",
+                &[(
+                    "config --get-all remote.origin.push",
+                    "refs/heads/*:refs/heads/me/*"
+                )]
+            )),
+            ["me/feature/login", "me/main", "me/wip"]
+        );
+    }
+
+    #[test]
+    fn test_push_update_classes() {
+        let update = |cmd: &str| updates(&repo(cmd, &[]));
+        assert_eq!(
+            update("push origin main"),
+            ["branch:fast-forward refs/heads/main"]
+        );
+        assert_eq!(update("push origin wip"), ["branch:create refs/heads/wip"]);
+        assert_eq!(
+            update("push origin +main"),
+            ["branch:force refs/heads/main"]
+        );
+        assert_eq!(
+            update("push -f origin main"),
+            ["branch:force refs/heads/main"]
+        );
+        assert_eq!(
+            update("push --force-with-lease origin HEAD:main"),
+            ["branch:force refs/heads/main"]
+        );
+        // Nothing to overwrite yet
+        assert_eq!(update("push origin +wip"), ["branch:create refs/heads/wip"]);
+        assert_eq!(
+            update("push origin :main"),
+            ["branch:delete refs/heads/main"]
+        );
+        assert_eq!(
+            update("push -d origin wip"),
+            ["branch:delete refs/heads/wip"]
+        );
+        assert_eq!(update("push origin v1.0"), ["tag:create refs/tags/v1.0"]);
+        assert_eq!(update("push origin +v1.0"), ["tag:force refs/tags/v1.0"]);
+        assert_eq!(
+            update("push origin ^refs/heads/wip refs/notes/*:refs/notes/*"),
+            Vec::<String>::new()
+        );
+        // An unfetched remote tells nothing, so forcing is assumed to overwrite
+        assert_eq!(
+            update("push -f git@example.com:me/app.git main"),
+            ["branch:force refs/heads/main"]
+        );
+    }
+
+    #[test]
+    fn test_push_update_mirror_prune_tags() {
+        let tags = (
+            "for-each-ref --format=%(refname) refs/tags/",
+            "refs/tags/v1.0\nrefs/tags/v2.0",
+        );
+        assert_eq!(
+            updates(&repo("push --tags origin", &[tags])),
+            ["tag:create refs/tags/v1.0", "tag:create refs/tags/v2.0"]
+        );
+        assert_eq!(
+            updates(&repo("push origin main --tags", &[tags])),
+            [
+                "branch:fast-forward refs/heads/main",
+                "tag:create refs/tags/v1.0",
+                "tag:create refs/tags/v2.0"
+            ]
+        );
+
+        // `feature/login` is gone locally but still on origin
+        let mirror = repo(
+            "push --mirror origin",
+            &[(
+                "for-each-ref --format=%(refname) refs/",
+                "refs/heads/main\nrefs/heads/wip\nrefs/tags/v1.0",
+            )],
+        );
+        assert_eq!(
+            updates(&mirror),
+            [
+                "branch:force refs/heads/main",
+                "branch:create refs/heads/wip",
+                "tag:force refs/tags/v1.0",
+                "branch:delete refs/heads/feature/login"
+            ]
+        );
+
+        let gone = (
+            "for-each-ref --format=%(refname) refs/remotes/origin/",
+            "refs/remotes/origin/HEAD\nrefs/remotes/origin/main\nrefs/remotes/origin/gone",
+        );
+        assert_eq!(
+            updates(&repo(
+                "push --prune origin refs/heads/*:refs/heads/*",
+                &[gone]
+            )),
+            [
+                "branch:create refs/heads/feature/login",
+                "branch:fast-forward refs/heads/main",
+                "branch:create refs/heads/wip",
+                "branch:delete refs/heads/gone"
+            ]
+        );
+        assert_eq!(
+            updates(&repo("push --all origin", &[gone])),
+            [
+                "branch:create refs/heads/feature/login",
+                "branch:fast-forward refs/heads/main",
+                "branch:create refs/heads/wip"
+            ]
+        );
+
+        // A forcing refspec in config, as `git push` alone would use it
+        assert_eq!(
+            updates(&repo(
+                "push",
+                &[(
+                    "config --get-all remote.origin.push",
+                    "+refs/heads/main:refs/heads/main"
+                )]
+            )),
+            ["branch:force refs/heads/main"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        for url in [
+            "https://github.com/Our/App.git",
+            "https://token@GitHub.com/Our/App/",
+            "git@github.com:Our/App.git",
+            "ssh://git@github.com:22/Our/App",
+        ] {
+            assert_eq!(normalize_url(url), "github.com/Our/App", "{}", url);
+        }
+        assert_eq!(normalize_url("/srv/git/app.git"), "/srv/git/app");
+        assert_eq!(normalize_url("file:///srv/git/app.git"), "/srv/git/app");
+        assert_eq!(normalize_url("../app"), "../app");
+    }
+
+    #[test]
+    fn test_remote_urls() {
+        let answers = [
+            (
+                "remote get-url --all origin",
+                "https://github.com/me/app.git",
+            ),
+            (
+                "remote get-url --all --push origin",
+                "git@github.com:me/app.git\ngit@gitlab.com:me/app.git",
+            ),
+            ("remote", "origin\nupstream"),
+            (
+                "remote get-url --all upstream",
+                "https://github.com/org/app.git",
+            ),
+            (
+                "config --get-regexp ^url\\..*\\.(push)?insteadof$",
+                "url.https://github.com/.insteadof gh:\n\
+                 url.git@github.com:.pushinsteadof gh:\n\
+                 url.https://github.com/org/.insteadof gh:org/",
+            ),
+        ];
+        let remotes = |cmd: &str| repo(cmd, &answers).remotes().unwrap();
+        assert_eq!(remotes("push"), ["github.com/me/app", "gitlab.com/me/app"]);
+        assert_eq!(remotes("fetch"), ["github.com/me/app"]);
+        assert_eq!(remotes("pull upstream main"), ["github.com/org/app"]);
+        assert_eq!(
+            remotes("fetch --all"),
+            ["github.com/me/app", "github.com/org/app"]
+        );
+        assert_eq!(
+            remotes("fetch --multiple origin upstream"),
+            ["github.com/me/app", "github.com/org/app"]
+        );
+        // Bare URLs, rewritten as git would
+        assert_eq!(
+            remotes("push https://GitHub.com/org/app.git main"),
+            ["github.com/org/app"]
+        );
+        assert_eq!(remotes("fetch gh:org/app"), ["github.com/org/app"]);
+        assert_eq!(remotes("fetch gh:me/app"), ["github.com/me/app"]);
+        assert_eq!(
+            repo("push gh:me/app", &answers).remote_urls(),
+            Some(&["git@github.com:me/app".to_string()][..])
+        );
+        assert!(remotes("status").is_empty());
+        // A name git doesn't know, or no remotes at all, can't be resolved
+        assert_eq!(repo("push typo main", &answers).remotes(), None);
+        assert_eq!(repo("fetch --multiple", &answers).remotes(), None);
+        assert_eq!(
+            repo("push ../app main", &answers).remotes(),
+            Some(vec!["../app".to_string()])
+        );
+    }
+}
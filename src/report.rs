@@ -0,0 +1,149 @@
+use crate::detect::Tool;
+use crate::rules::{Decision, Matched};
+use serde_json::{Value, json};
+
+/// Why gg stopped or paused a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Blocked,
+    Confirm,
+    /// Waiting for `gg approve`
+    Pending,
+    Undetected,
+    ConfigError,
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Blocked => write!(f, "blocked"),
+            Event::Confirm => write!(f, "confirm"),
+            Event::Pending => write!(f, "pending"),
+            Event::Undetected => write!(f, "undetected"),
+            Event::ConfigError => write!(f, "config_error"),
+        }
+    }
+}
+
+/// Everything a caller needs to react to a block without parsing text
+#[derive(Debug)]
+pub struct Report<'a> {
+    pub event: Event,
+    pub tool: Option<Tool>,
+    pub args: &'a [String],
+    pub decision: Option<Decision>,
+    pub matched: Option<&'a Matched>,
+    pub message: String,
+    pub suggestions: Vec<String>,
+    /// Only set for `Event::Confirm`
+    pub confirmed: Option<bool>,
+    pub exit_code: Option<u8>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(event: Event, args: &'a [String], message: String) -> Self {
+        Self {
+            event,
+            tool: None,
+            args,
+            decision: None,
+            matched: None,
+            message,
+            suggestions: Vec::new(),
+            confirmed: None,
+            exit_code: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut object = json!({
+            "event": self.event.to_string(),
+            "tool": self.tool.map(|t| t.to_string()),
+            "args": self.args,
+            "decision": self.decision.map(|d| d.to_string().to_lowercase()),
+            "rule": self.matched.map(Matched::to_json),
+            "reason": self.matched.and_then(|m| m.reason.clone()),
+            "severity": self.matched.and_then(|m| m.severity).map(|s| s.to_string()),
+            "suggestions": self.suggestions,
+            "message": self.message,
+            "exit_code": self.exit_code,
+        });
+        if let (Some(confirmed), Value::Object(map)) = (self.confirmed, &mut object) {
+            map.insert("confirmed".into(), Value::Bool(confirmed));
+        }
+        object.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Severity;
+    use crate::rules::RuleList;
+    use std::path::PathBuf;
+
+    fn parse(report: &Report) -> Value {
+        serde_json::from_str(&report.to_json()).unwrap()
+    }
+
+    #[test]
+    fn test_blocked_report() {
+        let args = vec!["push".to_string(), "--force".to_string()];
+        let matched = Matched {
+            list: RuleList::Table,
+            index: 0,
+            pattern: "\"push --force*\"".to_string(),
+            source: Some(PathBuf::from("/etc/gg.toml")),
+            id: Some("no-force-push".to_string()),
+            reason: Some("rewrites history".to_string()),
+            severity: Some(Severity::High),
+            suggest: Some("push --force-with-lease".to_string()),
+        };
+        let report = Report {
+            tool: Some(Tool::Git),
+            decision: Some(Decision::Deny),
+            matched: Some(&matched),
+            suggestions: vec!["gg push --force-with-lease".to_string()],
+            exit_code: Some(77),
+            ..Report::new(Event::Blocked, &args, "denied by policy".to_string())
+        };
+        let value = parse(&report);
+        assert_eq!(value["event"], "blocked");
+        assert_eq!(value["tool"], "git");
+        assert_eq!(value["args"], json!(["push", "--force"]));
+        assert_eq!(value["decision"], "deny");
+        assert_eq!(value["rule"]["list"], "rule");
+        assert_eq!(value["rule"]["id"], "no-force-push");
+        assert_eq!(value["rule"]["source"], "/etc/gg.toml");
+        assert_eq!(value["reason"], "rewrites history");
+        assert_eq!(value["severity"], "high");
+        assert_eq!(value["suggestions"], json!(["gg push --force-with-lease"]));
+        assert_eq!(value["exit_code"], 77);
+        assert!(value.get("confirmed").is_none());
+    }
+
+    #[test]
+    fn test_undetected_report_has_nulls() {
+        let args = vec!["status".to_string()];
+        let report = Report::new(Event::Undetected, &args, "ambiguous".to_string());
+        let value = parse(&report);
+        assert_eq!(value["event"], "undetected");
+        assert!(value["tool"].is_null());
+        assert!(value["rule"].is_null());
+        assert!(value["reason"].is_null());
+        assert_eq!(value["suggestions"], json!([]));
+    }
+
+    #[test]
+    fn test_confirm_report_includes_outcome() {
+        let args = vec!["push".to_string()];
+        let report = Report {
+            decision: Some(Decision::Confirm),
+            confirmed: Some(false),
+            ..Report::new(Event::Confirm, &args, "cancelled".to_string())
+        };
+        let value = parse(&report);
+        assert_eq!(value["decision"], "confirm");
+        assert_eq!(value["confirmed"], false);
+    }
+}
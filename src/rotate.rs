@@ -0,0 +1,289 @@
+use crate::chain;
+use crate::config::Options;
+use crate::logger;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use fs4::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Advisory lock on `<log>.lock`, released when dropped. Writers hold it
+/// exclusively from reading the chain head to appending the record, which
+/// also covers rotation; readers share it.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    pub fn shared(log: &Path) -> io::Result<Lock> {
+        let file = Self::open(log)?;
+        FileExt::lock_shared(&file)?;
+        Ok(Lock { _file: file })
+    }
+
+    pub fn exclusive(log: &Path) -> io::Result<Lock> {
+        let file = Self::open(log)?;
+        FileExt::lock(&file)?;
+        Ok(Lock { _file: file })
+    }
+
+    fn open(log: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(sibling(log, ".lock"))
+    }
+}
+
+/// Replace `path` with what `write` puts in a temporary sibling, by rename,
+/// so a reader or a crash never sees a half-written file
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp = sibling(path, &format!(".{}.tmp", std::process::id()));
+    let written = File::create(&tmp).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Rotate `log` if it has reached `log_max_size`. The caller holds the
+/// exclusive lock.
+pub fn rotate_if_needed(log: &Path, options: &Options) -> io::Result<()> {
+    let Some(max_size) = options.log_max_size else {
+        return Ok(());
+    };
+    if !is_full(log, max_size) {
+        return Ok(());
+    }
+    let key = logger::hmac_key(options);
+    rotate(log, options.log_keep, options.log_compress, key.as_deref())
+}
+
+fn is_full(log: &Path, max_size: u64) -> bool {
+    fs::metadata(log).is_ok_and(|m| m.len() > 0 && m.len() >= max_size)
+}
+
+/// Shift `<log>.N` to `<log>.N+1`, move `log` to `<log>.1` and delete what no
+/// longer fits in `keep`
+fn rotate(log: &Path, keep: usize, compress: bool, key: Option<&[u8]>) -> io::Result<()> {
+    let existing = count_rotated(log);
+
+    // Newest first; with `keep = 0` the log itself goes too
+    let mut dropped: Vec<PathBuf> = (keep.max(1)..=existing)
+        .filter_map(|n| find_rotated(log, n))
+        .collect();
+    if keep == 0 {
+        dropped.insert(0, log.to_path_buf());
+    }
+    // The chain carries on from the last record being deleted
+    if let Some(base) = dropped
+        .iter()
+        .find_map(|path| read(path).ok().and_then(|c| chain::last_head(&c)))
+    {
+        base.write_base(log, key)?;
+    }
+    for path in &dropped {
+        fs::remove_file(path)?;
+    }
+    if keep == 0 {
+        return Ok(());
+    }
+
+    for n in (1..keep.min(existing + 1)).rev() {
+        if let Some(from) = find_rotated(log, n) {
+            let gz = from.extension().is_some_and(|e| e == "gz");
+            fs::rename(&from, rotated(log, n + 1, gz))?;
+        }
+    }
+    let first = rotated(log, 1, false);
+    fs::rename(log, &first)?;
+    if compress {
+        gzip(&first, &rotated(log, 1, true))?;
+    }
+    Ok(())
+}
+
+/// Compress `from` into `to` and remove `from`. Written atomically so a
+/// crash never leaves a truncated archive behind.
+fn gzip(from: &Path, to: &Path) -> io::Result<()> {
+    write_atomic(to, |file| {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        io::copy(&mut File::open(from)?, &mut encoder)?;
+        encoder.finish().map(drop)
+    })?;
+    fs::remove_file(from)
+}
+
+/// The log and its rotated files, oldest first
+pub fn segments(log: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = (1..=count_rotated(log))
+        .rev()
+        .filter_map(|n| find_rotated(log, n))
+        .collect();
+    if log.exists() {
+        paths.push(log.to_path_buf());
+    }
+    paths
+}
+
+/// The whole retained log, rotated files included, oldest record first
+pub fn read_all(log: &Path) -> io::Result<String> {
+    // Best effort: readers may not be allowed to create the lock file
+    let _lock = Lock::shared(log).ok();
+    let paths = segments(log);
+    if paths.is_empty() {
+        return fs::read_to_string(log);
+    }
+    let mut content = String::new();
+    for path in paths {
+        content.push_str(&read(&path)?);
+    }
+    Ok(content)
+}
+
+fn read(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    if path.extension().is_some_and(|e| e == "gz") {
+        GzDecoder::new(File::open(path)?).read_to_string(&mut content)?;
+    } else {
+        File::open(path)?.read_to_string(&mut content)?;
+    }
+    Ok(content)
+}
+
+/// How many of `<log>.1`, `<log>.2`, ... exist without a gap
+fn count_rotated(log: &Path) -> usize {
+    (1..)
+        .take_while(|&n| find_rotated(log, n).is_some())
+        .count()
+}
+
+fn find_rotated(log: &Path, n: usize) -> Option<PathBuf> {
+    [false, true]
+        .into_iter()
+        .map(|gz| rotated(log, n, gz))
+        .find(|path| path.exists())
+}
+
+fn rotated(log: &Path, n: usize, gz: bool) -> PathBuf {
+    let suffix = if gz { ".gz" } else { "" };
+    sibling(log, &format!(".{}{}", n, suffix))
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Head;
+
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("audit.log");
+        (dir, log)
+    }
+
+    fn options(max_size: u64, keep: usize, compress: bool) -> Options {
+        Options {
+            log_max_size: Some(max_size),
+            log_keep: keep,
+            log_compress: compress,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_rotates_and_keeps_n_files() {
+        let (dir, log) = setup("gg_test_rotate_keep");
+        let options = options(4, 2, false);
+        for record in ["one\n", "two\n", "three\n", "four\n"] {
+            rotate_if_needed(&log, &options).unwrap();
+            fs::write(&log, record).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&log).unwrap(), "four\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("audit.log.1")).unwrap(),
+            "three\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("audit.log.2")).unwrap(),
+            "two\n"
+        );
+        assert!(!dir.join("audit.log.3").exists());
+        assert_eq!(read_all(&log).unwrap(), "two\nthree\nfour\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_small_log_is_not_rotated() {
+        let (dir, log) = setup("gg_test_rotate_small");
+        fs::write(&log, "short\n").unwrap();
+        rotate_if_needed(&log, &options(1024, 2, false)).unwrap();
+        rotate_if_needed(&log, &Options::default()).unwrap();
+        assert_eq!(segments(&log), vec![log.clone()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compressed_rotation_reads_back() {
+        let (dir, log) = setup("gg_test_rotate_gzip");
+        let options = options(1, 3, true);
+        fs::write(&log, "old\n").unwrap();
+        rotate_if_needed(&log, &options).unwrap();
+        fs::write(&log, "new\n").unwrap();
+        rotate_if_needed(&log, &options).unwrap();
+        fs::write(&log, "current\n").unwrap();
+
+        assert!(dir.join("audit.log.1.gz").exists());
+        assert!(dir.join("audit.log.2.gz").exists());
+        assert!(!dir.join("audit.log.1").exists());
+        assert_eq!(read_all(&log).unwrap(), "old\nnew\ncurrent\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_dropping_chained_records_writes_base() {
+        let (dir, log) = setup("gg_test_rotate_base");
+        let mut head = Head::genesis();
+        let mut seal = |line: &str| {
+            let (sealed, next) = chain::seal(line, &head, None);
+            head = next;
+            format!("{}\n", sealed)
+        };
+        let options = options(1, 1, false);
+        fs::write(&log, seal("[2026-01-01 00:00:00] ALLOW | git status")).unwrap();
+        rotate_if_needed(&log, &options).unwrap();
+        assert!(Head::read_base_checked(&log, None).is_none());
+
+        fs::write(&log, seal("[2026-01-01 00:00:01] ALLOW | git log")).unwrap();
+        rotate_if_needed(&log, &options).unwrap();
+        let (base, _) = Head::read_base_checked(&log, None).unwrap();
+        assert_eq!(base.seq, 1);
+
+        let content = read_all(&log).unwrap();
+        assert!(chain::verify(&content, Some(&base), Some(&head), None).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
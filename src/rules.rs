@@ -1,7 +1,14 @@
-use crate::config::Rules;
+use crate::argv::CommandArgs;
+use crate::config::{Action, MatchRule, Pattern, Rules, Severity};
+use crate::detect::Tool;
+use crate::grants::Grant;
+use crate::repo::{self, Repo};
 use glob_match::glob_match;
+use serde::Deserialize;
+use std::path::PathBuf;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Decision {
     Allow,
     Confirm,
@@ -20,47 +27,416 @@ impl std::fmt::Display for Decision {
     }
 }
 
+/// A command prepared for matching: the raw command line plus, for git, its
+/// normalized forms (global options dropped, flags split and reordered), and
+/// the parsed arguments that structured rules are checked against.
+struct Subject {
+    forms: Vec<String>,
+    /// The forms allow rules see: global options are kept in front, since
+    /// `-c core.sshCommand=...` turns an allowed push into something else
+    allow_forms: Vec<String>,
+    parsed: CommandArgs,
+    /// For branch conditions
+    repo: Repo,
+}
+
+impl Subject {
+    fn new(tool: Tool, args: &[String]) -> Self {
+        let parsed = CommandArgs::parse(tool, args);
+        let mut forms = vec![args.join(" ")];
+        if tool == Tool::Git {
+            for form in parsed.normalized_forms() {
+                if !forms.contains(&form) {
+                    forms.push(form);
+                }
+            }
+        }
+        let allow_forms = if parsed.globals.is_empty() {
+            forms.clone()
+        } else {
+            let globals: Vec<String> = parsed.globals.iter().map(|g| g.to_string()).collect();
+            let globals = globals.join(" ");
+            let mut allow_forms = vec![args.join(" ")];
+            allow_forms.extend(
+                forms[1..]
+                    .iter()
+                    .map(|form| format!("{} {}", globals, form)),
+            );
+            allow_forms
+        };
+        let repo = Repo::for_command(tool, &parsed);
+        Self {
+            forms,
+            allow_forms,
+            parsed,
+            repo,
+        }
+    }
+
+    fn matches(&self, pattern: &str) -> bool {
+        self.forms
+            .iter()
+            .any(|command| matches_pattern(pattern, command))
+    }
+
+    fn matches_allowed(&self, pattern: &str) -> bool {
+        self.allow_forms
+            .iter()
+            .any(|command| matches_pattern(pattern, command))
+    }
+
+    fn matches_for(&self, action: Action, pattern: &str) -> bool {
+        match action {
+            Action::Allow => self.matches_allowed(pattern),
+            Action::Confirm | Action::Deny => self.matches(pattern),
+        }
+    }
+
+    /// The first pattern or table rule with `action` that matches.
+    /// Every rule tested along the way is recorded in `trace`.
+    fn find(
+        &self,
+        rules: &Rules,
+        action: Action,
+        mut trace: Option<&mut Vec<Step>>,
+    ) -> Option<Matched> {
+        let patterns = rules.patterns(action).iter().enumerate().map(|(index, p)| {
+            (
+                Matched::pattern(action, index, p),
+                self.matches_for(action, p),
+            )
+        });
+        let tables = rules
+            .matches
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.action == action)
+            .map(|(index, r)| (Matched::table(index, r), self.matches_rule(r)));
+
+        for (rule, hit) in patterns.chain(tables) {
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.push(Step {
+                    rule: rule.clone(),
+                    matched: hit,
+                });
+            }
+            if hit {
+                return Some(rule);
+            }
+        }
+        None
+    }
+
+    /// An allow table rule without a `pattern` has nothing to spell global
+    /// options with, so it doesn't match a command that has any
+    fn matches_rule(&self, rule: &MatchRule) -> bool {
+        let pattern = match &rule.pattern {
+            Some(p) => self.matches_for(rule.action, p),
+            None => rule.action != Action::Allow || self.parsed.globals.is_empty(),
+        };
+        pattern && matches_args(rule, &self.parsed) && self.matches_repo(rule)
+    }
+
+    /// Each repository condition that is set has a glob matching one of its
+    /// branches, updates or remotes. `push_target` and `push_update` must
+    /// both hold for the same pushed ref. Git is only asked about the
+    /// conditions a rule uses.
+    fn matches_repo(&self, rule: &MatchRule) -> bool {
+        let hit = |globs: &[String], branches: &[&str]| {
+            globs.iter().any(|g| {
+                self.repo
+                    .expand(g)
+                    .is_some_and(|g| branches.iter().any(|b| glob(&g, b)))
+            })
+        };
+        (rule.branch.is_empty() || hit(&rule.branch, self.repo.current_branch().as_slice()))
+            && (rule.upstream.is_empty() || hit(&rule.upstream, self.repo.upstream().as_slice()))
+            && (rule.push_target.is_empty() && rule.push_update.is_empty()
+                || self.repo.push_specs().iter().any(|spec| {
+                    (rule.push_target.is_empty()
+                        || hit(&rule.push_target, spec.branch().as_slice()))
+                        && (rule.push_update.is_empty()
+                            || rule
+                                .push_update
+                                .iter()
+                                .any(|u| glob(&repo::update_pattern(u), &spec.label())))
+                }))
+            && (rule.remote.is_empty()
+                || match self.repo.remotes() {
+                    Some(remotes) => remotes
+                        .iter()
+                        .any(|r| rule.remote.iter().any(|g| glob(g, r))),
+                    // A remote that can't be resolved never escapes a deny or
+                    // confirm rule
+                    None => rule.action != Action::Allow,
+                })
+    }
+}
+
+/// Which list of a tool's rules an entry lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleList {
+    Allow,
+    Confirm,
+    Deny,
+    /// `[[git.rules.match]]` / `[[git.rule]]` tables
+    Table,
+    /// Added at runtime with `gg grant`
+    Grant,
+}
+
+impl From<Action> for RuleList {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Allow => RuleList::Allow,
+            Action::Confirm => RuleList::Confirm,
+            Action::Deny => RuleList::Deny,
+        }
+    }
+}
+
+impl std::fmt::Display for RuleList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleList::Allow => write!(f, "allow"),
+            RuleList::Confirm => write!(f, "confirm"),
+            RuleList::Deny => write!(f, "deny"),
+            RuleList::Table => write!(f, "rule"),
+            RuleList::Grant => write!(f, "grant"),
+        }
+    }
+}
+
+/// The rule that produced a decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matched {
+    pub list: RuleList,
+    /// Position within `list`
+    pub index: usize,
+    pub pattern: String,
+    /// Config file the rule was loaded from
+    pub source: Option<PathBuf>,
+    pub id: Option<String>,
+    pub reason: Option<String>,
+    pub severity: Option<Severity>,
+    pub suggest: Option<String>,
+}
+
+impl Matched {
+    fn pattern(action: Action, index: usize, pattern: &Pattern) -> Self {
+        Self {
+            list: RuleList::from(action),
+            index,
+            pattern: pattern.text.clone(),
+            source: pattern.source.clone(),
+            id: None,
+            reason: None,
+            severity: None,
+            suggest: None,
+        }
+    }
+
+    /// `{list, index, pattern, id, source}` for JSON output and logs
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "list": self.list.to_string(),
+            "index": self.index,
+            "pattern": self.pattern,
+            "id": self.id,
+            "source": self.source.as_ref().map(|p| p.display().to_string()),
+        })
+    }
+
+    fn table(index: usize, rule: &MatchRule) -> Self {
+        Self {
+            list: RuleList::Table,
+            index,
+            pattern: rule.to_string(),
+            source: rule.source.clone(),
+            id: rule.id.clone(),
+            reason: rule.reason.clone(),
+            severity: rule.severity,
+            suggest: rule.suggest.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Matched {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Table rules are already rendered with their pattern quoted
+        if self.list == RuleList::Table {
+            write!(f, "{}[{}] {}", self.list, self.index, self.pattern)?;
+        } else {
+            write!(f, "{}[{}] {:?}", self.list, self.index, self.pattern)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " (id: {})", id)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " in {}", source.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// One rule tested during evaluation, for `gg explain`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub rule: Matched,
+    pub matched: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub decision: Decision,
+    /// None when no rule matched and the default applied
+    pub matched: Option<Matched>,
+}
+
 /// Check if any rule in a RuleSet matches the given args
-pub fn has_any_match(rules: &Rules, args: &[String]) -> bool {
-    let command = args.join(" ");
+pub fn has_any_match(tool: Tool, rules: &Rules, args: &[String]) -> bool {
+    let subject = Subject::new(tool, args);
     rules
         .allow
         .iter()
         .chain(rules.confirm.iter())
         .chain(rules.deny.iter())
-        .any(|p| matches_pattern(p, &command))
+        .any(|p| subject.matches(p))
+        || rules.matches.iter().any(|r| subject.matches_rule(r))
 }
 
 /// Evaluate args against a specific tool's rules
-pub fn evaluate(rules: &Rules, args: &[String], deny_by_default: bool) -> Decision {
-    let command = args.join(" ");
+pub fn evaluate(tool: Tool, rules: &Rules, args: &[String], deny_by_default: bool) -> Evaluation {
+    evaluate_inner(Subject::new(tool, args), rules, &[], deny_by_default, None)
+}
 
-    for pattern in &rules.deny {
-        if matches_pattern(pattern, &command) {
-            return Decision::Deny;
-        }
+/// Like `evaluate`, allowing anything one of `grants` matches that would
+/// otherwise need confirming or be denied by default. `grants` must already
+/// be this tool's, in scope and unexpired.
+pub fn evaluate_granted(
+    tool: Tool,
+    rules: &Rules,
+    grants: &[Grant],
+    args: &[String],
+    deny_by_default: bool,
+) -> Evaluation {
+    evaluate_inner(
+        Subject::new(tool, args),
+        rules,
+        grants,
+        deny_by_default,
+        None,
+    )
+}
+
+/// Like `evaluate_granted`, also returning every rule tested in
+/// deny → confirm → allow → grant order
+pub fn evaluate_traced(
+    tool: Tool,
+    rules: &Rules,
+    grants: &[Grant],
+    args: &[String],
+    deny_by_default: bool,
+) -> (Evaluation, Vec<Step>) {
+    let mut trace = Vec::new();
+    let subject = Subject::new(tool, args);
+    let eval = evaluate_inner(subject, rules, grants, deny_by_default, Some(&mut trace));
+    (eval, trace)
+}
+
+fn evaluate_inner(
+    subject: Subject,
+    rules: &Rules,
+    grants: &[Grant],
+    deny_by_default: bool,
+    mut trace: Option<&mut Vec<Step>>,
+) -> Evaluation {
+    let decided = |decision, matched| Evaluation { decision, matched };
+    if let Some(matched) = subject.find(rules, Action::Deny, trace.as_deref_mut()) {
+        return decided(Decision::Deny, Some(matched));
     }
+    // A grant stands in for a human's yes, so it only lifts a confirm or the
+    // default deny, never a deny rule
+    let undecided = match subject.find(rules, Action::Confirm, trace.as_deref_mut()) {
+        Some(matched) => decided(Decision::Confirm, Some(matched)),
+        None => match subject.find(rules, Action::Allow, trace.as_deref_mut()) {
+            Some(matched) => return decided(Decision::Allow, Some(matched)),
+            None if !deny_by_default => return decided(Decision::Allow, None),
+            None => decided(Decision::DefaultDeny, None),
+        },
+    };
 
-    for pattern in &rules.confirm {
-        if matches_pattern(pattern, &command) {
-            return Decision::Confirm;
+    for (index, grant) in grants.iter().enumerate() {
+        let rule = grant.to_matched(index);
+        let matched = subject.matches_allowed(&grant.pattern);
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.push(Step {
+                rule: rule.clone(),
+                matched,
+            });
+        }
+        if matched {
+            return decided(Decision::Allow, Some(rule));
         }
     }
+    undecided
+}
+
+/// Check the structured fields of a table rule against the parsed command
+fn matches_args(rule: &MatchRule, cmd: &CommandArgs) -> bool {
+    let mut operands: &[String] = &cmd.positionals;
 
-    for pattern in &rules.allow {
-        if matches_pattern(pattern, &command) {
-            return Decision::Allow;
+    if let Some(subcommand) = &rule.subcommand {
+        let mut words = subcommand.split_whitespace();
+        match (words.next(), &cmd.subcommand) {
+            (Some(word), Some(sub)) if glob(word, sub) => {}
+            _ => return false,
         }
+        for word in words {
+            match operands.split_first() {
+                Some((first, rest)) if glob(word, first) => operands = rest,
+                _ => return false,
+            }
+        }
+    }
+
+    if rule.positionals.len() > operands.len()
+        || !rule
+            .positionals
+            .iter()
+            .zip(operands)
+            .all(|(p, o)| glob(p, o))
+    {
+        return false;
     }
 
-    if deny_by_default {
-        Decision::DefaultDeny
+    let hit = |pattern: &String| matches_argument(pattern, cmd);
+    (rule.any.is_empty() || rule.any.iter().any(hit))
+        && rule.all.iter().all(hit)
+        && !rule.none.iter().any(hit)
+}
+
+/// Patterns starting with `-` match options (by name or as `name=value`);
+/// anything else matches operands, including pathspecs after `--`.
+fn matches_argument(pattern: &str, cmd: &CommandArgs) -> bool {
+    if pattern.starts_with('-') {
+        cmd.options
+            .iter()
+            .any(|o| glob(pattern, &o.name) || glob(pattern, &o.to_string()))
     } else {
-        Decision::Allow
+        cmd.positionals
+            .iter()
+            .chain(cmd.after_separator.iter().flatten())
+            .any(|o| glob(pattern, o))
     }
 }
 
-fn matches_pattern(pattern: &str, command: &str) -> bool {
+fn glob(pattern: &str, text: &str) -> bool {
+    pattern == text || glob_match(&pattern.replace('*', "**"), text)
+}
+
+/// Whether a config pattern matches a space-joined command line
+pub fn matches_pattern(pattern: &str, command: &str) -> bool {
     if pattern == command {
         return true;
     }
@@ -86,9 +462,32 @@ mod tests {
 
     fn make_rules(allow: Vec<&str>, confirm: Vec<&str>, deny: Vec<&str>) -> Rules {
         Rules {
-            allow: allow.into_iter().map(String::from).collect(),
-            confirm: confirm.into_iter().map(String::from).collect(),
-            deny: deny.into_iter().map(String::from).collect(),
+            allow: allow.into_iter().map(Pattern::from).collect(),
+            confirm: confirm.into_iter().map(Pattern::from).collect(),
+            deny: deny.into_iter().map(Pattern::from).collect(),
+            ..Rules::default()
+        }
+    }
+
+    fn match_rule(action: Action, subcommand: &str) -> MatchRule {
+        MatchRule {
+            action,
+            id: None,
+            reason: None,
+            severity: None,
+            suggest: None,
+            pattern: None,
+            subcommand: Some(subcommand.to_string()),
+            any: vec![],
+            all: vec![],
+            none: vec![],
+            positionals: vec![],
+            branch: vec![],
+            upstream: vec![],
+            push_target: vec![],
+            push_update: vec![],
+            remote: vec![],
+            source: None,
         }
     }
 
@@ -99,14 +498,17 @@ mod tests {
     #[test]
     fn test_exact_allow() {
         let rules = make_rules(vec!["pr list"], vec![], vec![]);
-        assert_eq!(evaluate(&rules, &args("pr list"), true), Decision::Allow);
+        assert_eq!(
+            evaluate(Tool::Gh, &rules, &args("pr list"), true).decision,
+            Decision::Allow
+        );
     }
 
     #[test]
     fn test_prefix_allow() {
         let rules = make_rules(vec!["pr list"], vec![], vec![]);
         assert_eq!(
-            evaluate(&rules, &args("pr list --json url"), true),
+            evaluate(Tool::Gh, &rules, &args("pr list --json url"), true).decision,
             Decision::Allow
         );
     }
@@ -115,7 +517,7 @@ mod tests {
     fn test_glob_allow() {
         let rules = make_rules(vec!["api GET *"], vec![], vec![]);
         assert_eq!(
-            evaluate(&rules, &args("api GET /repos/foo/bar"), true),
+            evaluate(Tool::Gh, &rules, &args("api GET /repos/foo/bar"), true).decision,
             Decision::Allow
         );
     }
@@ -123,14 +525,17 @@ mod tests {
     #[test]
     fn test_deny_priority_over_allow() {
         let rules = make_rules(vec!["pr *"], vec![], vec!["pr merge"]);
-        assert_eq!(evaluate(&rules, &args("pr merge"), true), Decision::Deny);
+        assert_eq!(
+            evaluate(Tool::Gh, &rules, &args("pr merge"), true).decision,
+            Decision::Deny
+        );
     }
 
     #[test]
     fn test_confirm() {
         let rules = make_rules(vec![], vec!["pr create"], vec![]);
         assert_eq!(
-            evaluate(&rules, &args("pr create"), true),
+            evaluate(Tool::Gh, &rules, &args("pr create"), true).decision,
             Decision::Confirm
         );
     }
@@ -139,7 +544,7 @@ mod tests {
     fn test_default_deny() {
         let rules = make_rules(vec!["pr list"], vec![], vec![]);
         assert_eq!(
-            evaluate(&rules, &args("repo delete foo"), true),
+            evaluate(Tool::Gh, &rules, &args("repo delete foo"), true).decision,
             Decision::DefaultDeny
         );
     }
@@ -147,15 +552,21 @@ mod tests {
     #[test]
     fn test_default_allow() {
         let rules = make_rules(vec![], vec![], vec![]);
-        assert_eq!(evaluate(&rules, &args("anything"), false), Decision::Allow);
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("anything"), false).decision,
+            Decision::Allow
+        );
     }
 
     #[test]
     fn test_deny_over_confirm() {
         let rules = make_rules(vec![], vec!["pr *"], vec!["pr merge"]);
-        assert_eq!(evaluate(&rules, &args("pr merge"), true), Decision::Deny);
         assert_eq!(
-            evaluate(&rules, &args("pr create"), true),
+            evaluate(Tool::Gh, &rules, &args("pr merge"), true).decision,
+            Decision::Deny
+        );
+        assert_eq!(
+            evaluate(Tool::Gh, &rules, &args("pr create"), true).decision,
             Decision::Confirm
         );
     }
@@ -163,21 +574,371 @@ mod tests {
     #[test]
     fn test_has_any_match() {
         let rules = make_rules(vec!["push"], vec![], vec!["push --force*"]);
-        assert!(has_any_match(&rules, &args("push origin main")));
-        assert!(has_any_match(&rules, &args("push --force")));
-        assert!(!has_any_match(&rules, &args("pull")));
+        assert!(has_any_match(Tool::Git, &rules, &args("push origin main")));
+        assert!(has_any_match(Tool::Git, &rules, &args("push --force")));
+        assert!(!has_any_match(Tool::Git, &rules, &args("pull")));
     }
 
     #[test]
     fn test_git_push_force_deny() {
         let rules = make_rules(vec!["push"], vec![], vec!["push --force*", "push -f*"]);
         assert_eq!(
-            evaluate(&rules, &args("push --force origin main"), true),
+            evaluate(Tool::Git, &rules, &args("push --force origin main"), true).decision,
+            Decision::Deny
+        );
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("push origin main"), true).decision,
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn test_git_push_force_deny_any_order() {
+        let rules = make_rules(vec!["push"], vec![], vec!["push --force*", "push -f*"]);
+        for cmd in [
+            "push origin main --force",
+            "push -fu origin",
+            "push -uf origin",
+            "push --force-with-lease",
+            "-c foo=bar push -f",
+            "-C /tmp push origin --force main",
+        ] {
+            assert_eq!(
+                evaluate(Tool::Git, &rules, &args(cmd), true).decision,
+                Decision::Deny,
+                "{}",
+                cmd
+            );
+        }
+    }
+
+    #[test]
+    fn test_global_options_are_not_allowed_through() {
+        let mut rules = make_rules(vec!["push origin*", "status*"], vec![], vec![]);
+        rules.matches.push(match_rule(Action::Allow, "fetch"));
+        let eval = |cmd: &str| evaluate(Tool::Git, &rules, &args(cmd), true).decision;
+        assert_eq!(eval("push origin main"), Decision::Allow);
+        assert_eq!(
+            eval("-c core.sshCommand=touch push origin main"),
+            Decision::DefaultDeny
+        );
+        assert_eq!(eval("-c core.hooksPath=/x status"), Decision::DefaultDeny);
+        assert_eq!(eval("-C /other push origin main"), Decision::DefaultDeny);
+        assert_eq!(eval("fetch origin"), Decision::Allow);
+        assert_eq!(eval("-c x=y fetch origin"), Decision::DefaultDeny);
+
+        // A pattern that spells the global out still allows it
+        let rules = make_rules(vec!["--no-pager status*"], vec![], vec![]);
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("--no-pager status -s"), true).decision,
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn test_gh_args_are_not_normalized() {
+        let rules = make_rules(vec![], vec![], vec!["pr --admin*"]);
+        assert_eq!(
+            evaluate(Tool::Gh, &rules, &args("pr merge 1 --admin"), false).decision,
+            Decision::Allow
+        );
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("pr merge 1 --admin"), false).decision,
+            Decision::Deny
+        );
+    }
+
+    #[test]
+    fn test_match_rule_any_flag_or_refspec() {
+        let mut rules = make_rules(vec!["push*"], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            any: vec!["--force*".into(), "-f".into(), "+*".into()],
+            ..match_rule(Action::Deny, "push")
+        });
+        for cmd in [
+            "push origin main --force",
+            "push --force-with-lease=main origin",
+            "push -uf origin",
+            "push origin +main",
+        ] {
+            assert_eq!(
+                evaluate(Tool::Git, &rules, &args(cmd), true).decision,
+                Decision::Deny,
+                "{}",
+                cmd
+            );
+        }
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("push origin main"), true).decision,
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn test_match_rule_all_and_none() {
+        let mut rules = make_rules(vec![], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            all: vec!["-f".into(), "-d".into()],
+            none: vec!["-n".into(), "--dry-run".into()],
+            ..match_rule(Action::Deny, "clean")
+        });
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("clean -fd"), false).decision,
             Decision::Deny
         );
         assert_eq!(
-            evaluate(&rules, &args("push origin main"), true),
+            evaluate(Tool::Git, &rules, &args("clean -f"), false).decision,
+            Decision::Allow
+        );
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("clean -fdn"), false).decision,
             Decision::Allow
         );
     }
+
+    #[test]
+    fn test_match_rule_subcommand_words_and_positionals() {
+        let mut rules = make_rules(vec![], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            positionals: vec!["upstream".into()],
+            ..match_rule(Action::Confirm, "remote add")
+        });
+        rules.matches.push(match_rule(Action::Deny, "pr merge"));
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("remote -v add upstream url"), true).decision,
+            Decision::Confirm
+        );
+        assert_eq!(
+            evaluate(Tool::Git, &rules, &args("remote add fork url"), true).decision,
+            Decision::DefaultDeny
+        );
+        assert_eq!(
+            evaluate(Tool::Gh, &rules, &args("pr merge 12 --squash"), true).decision,
+            Decision::Deny
+        );
+        assert!(has_any_match(Tool::Gh, &rules, &args("pr merge 12")));
+        assert!(!has_any_match(Tool::Gh, &rules, &args("pr list")));
+    }
+
+    #[test]
+    fn test_table_rule_pattern_and_metadata() {
+        let mut rules = make_rules(vec!["push*"], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            pattern: Some("push --force*".into()),
+            id: Some("no-force-push".into()),
+            reason: Some("force-push rewrites shared history".into()),
+            severity: Some(Severity::High),
+            ..match_rule(Action::Deny, "*")
+        });
+        let eval = evaluate(Tool::Git, &rules, &args("push origin --force"), true);
+        assert_eq!(eval.decision, Decision::Deny);
+        let matched = eval.matched.unwrap();
+        assert_eq!(matched.list, RuleList::Table);
+        assert_eq!(matched.id.as_deref(), Some("no-force-push"));
+        assert_eq!(matched.severity, Some(Severity::High));
+        assert_eq!(
+            matched.to_string(),
+            r#"rule[0] "push --force*" subcommand="*" (id: no-force-push)"#
+        );
+
+        let eval = evaluate(Tool::Git, &rules, &args("push origin"), true);
+        assert_eq!(eval.decision, Decision::Allow);
+        assert_eq!(eval.matched.unwrap().list, RuleList::Allow);
+    }
+
+    #[test]
+    fn test_branch_conditions() {
+        let mut rules = make_rules(vec!["push*", "commit*"], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            push_target: vec!["${default_branch}".into(), "release/*".into()],
+            ..match_rule(Action::Deny, "push")
+        });
+        rules.matches.push(MatchRule {
+            branch: vec!["${default_branch}".into()],
+            ..match_rule(Action::Deny, "commit")
+        });
+        rules.matches.push(MatchRule {
+            upstream: vec!["release/*".into()],
+            ..match_rule(Action::Confirm, "commit")
+        });
+        // On `feature/x`, tracking `release/2`; origin's default branch is `trunk`
+        let eval = |cmd: &str, branch: &'static str| {
+            let mut subject = Subject::new(Tool::Git, &args(cmd));
+            subject.repo = Repo::with_git(subject.parsed.clone(), move |git| {
+                match git.join(" ").as_str() {
+                    "symbolic-ref --quiet --short HEAD" => Some(branch.to_string()),
+                    "symbolic-ref --quiet refs/remotes/origin/HEAD" => {
+                        Some("refs/remotes/origin/trunk".to_string())
+                    }
+                    "config --get branch.feature/x.merge" => {
+                        Some("refs/heads/release/2".to_string())
+                    }
+                    _ => None,
+                }
+            });
+            evaluate_inner(subject, &rules, &[], true, None).decision
+        };
+        assert_eq!(eval("push origin trunk", "feature/x"), Decision::Deny);
+        assert_eq!(
+            eval("push origin HEAD:release/3", "feature/x"),
+            Decision::Deny
+        );
+        assert_eq!(eval("push origin feature/x", "feature/x"), Decision::Allow);
+        // No refspec: `push.default = simple` pushes the current branch
+        assert_eq!(eval("push", "trunk"), Decision::Deny);
+        assert_eq!(eval("push", "feature/x"), Decision::Allow);
+
+        assert_eq!(eval("commit -m fix", "trunk"), Decision::Deny);
+        assert_eq!(eval("commit -m fix", "feature/x"), Decision::Confirm);
+        assert_eq!(eval("commit -m fix", "other"), Decision::Allow);
+    }
+
+    #[test]
+    fn test_push_update_conditions() {
+        let mut rules = make_rules(vec!["push*"], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            push_target: vec!["main".into()],
+            push_update: vec!["force".into(), "delete".into()],
+            ..match_rule(Action::Deny, "push")
+        });
+        rules.matches.push(MatchRule {
+            push_update: vec!["tag:*".into()],
+            ..match_rule(Action::Confirm, "push")
+        });
+        let eval = |cmd: &str| {
+            let mut subject = Subject::new(Tool::Git, &args(cmd));
+            subject.repo =
+                Repo::with_git(subject.parsed.clone(), |git| match git.join(" ").as_str() {
+                    "for-each-ref --format=%(refname) refs/remotes/origin/" => {
+                        Some("refs/remotes/origin/main\nrefs/remotes/origin/wip".to_string())
+                    }
+                    "show-ref --verify --quiet refs/tags/v1" => Some(String::new()),
+                    _ => None,
+                });
+            evaluate_inner(subject, &rules, &[], true, None).decision
+        };
+        assert_eq!(eval("push origin +main"), Decision::Deny);
+        assert_eq!(eval("push origin wip:main --force"), Decision::Deny);
+        assert_eq!(eval("push origin --delete main"), Decision::Deny);
+        assert_eq!(eval("push origin main"), Decision::Allow);
+        // The forced ref and `main` must be the same update
+        assert_eq!(eval("push origin +wip main"), Decision::Allow);
+        assert_eq!(eval("push origin v1"), Decision::Confirm);
+    }
+
+    #[test]
+    fn test_remote_conditions() {
+        let mut rules = make_rules(vec!["push*", "fetch*"], vec![], vec![]);
+        rules.matches.push(MatchRule {
+            remote: vec!["github.com/upstream-org/*".into()],
+            ..match_rule(Action::Deny, "push")
+        });
+        let eval = |cmd: &str| {
+            let mut subject = Subject::new(Tool::Git, &args(cmd));
+            subject.repo =
+                Repo::with_git(subject.parsed.clone(), |git| match git.join(" ").as_str() {
+                    "remote get-url --all --push origin" => {
+                        Some("git@github.com:our-fork/app.git".to_string())
+                    }
+                    "remote get-url --all --push upstream" => {
+                        Some("https://github.com/upstream-org/app.git".to_string())
+                    }
+                    _ => None,
+                });
+            evaluate_inner(subject, &rules, &[], true, None).decision
+        };
+        assert_eq!(eval("push origin main"), Decision::Allow);
+        assert_eq!(eval("push upstream main"), Decision::Deny);
+        assert_eq!(
+            eval("push git@github.com:upstream-org/app.git main"),
+            Decision::Deny
+        );
+        assert_eq!(eval("fetch upstream"), Decision::Allow);
+        // Unresolvable: the deny rule still applies
+        assert_eq!(eval("push typo main"), Decision::Deny);
+    }
+
+    #[test]
+    fn test_grants_never_override_deny() {
+        let mut rules = make_rules(
+            vec!["status*"],
+            vec!["push*"],
+            vec!["push --force*", "push -f*"],
+        );
+        rules.matches.push(MatchRule {
+            push_target: vec!["main".into()],
+            ..match_rule(Action::Deny, "push")
+        });
+        let grant = |pattern: &str| Grant {
+            id: "aaaaaa".into(),
+            tool: Tool::Git,
+            pattern: pattern.into(),
+            repo: None,
+            created: 0,
+            expires: i64::MAX,
+            uses_left: None,
+        };
+        let grants = [grant("push origin feat/*"), grant("status*")];
+        let eval = |cmd: &str| {
+            let mut subject = Subject::new(Tool::Git, &args(cmd));
+            subject.repo = Repo::with_git(subject.parsed.clone(), |_| None);
+            evaluate_inner(subject, &rules, &grants, true, None)
+        };
+        let granted = eval("push origin feat/x");
+        assert_eq!(granted.decision, Decision::Allow);
+        assert_eq!(granted.matched.unwrap().list, RuleList::Grant);
+
+        assert_eq!(eval("push origin feat/x --force").decision, Decision::Deny);
+        assert_eq!(eval("push -f origin feat/x").decision, Decision::Deny);
+        assert_eq!(eval("push origin feat/x:main").decision, Decision::Deny);
+        // Global options aren't granted any more than they're allowed
+        assert_eq!(
+            eval("-c core.sshCommand=x push origin feat/x").decision,
+            Decision::Confirm
+        );
+        // Already allowed: the grant isn't used up on it
+        assert_eq!(eval("status").matched.unwrap().list, RuleList::Allow);
+    }
+
+    #[test]
+    fn test_plain_patterns_win_within_a_class() {
+        let mut rules = make_rules(vec![], vec![], vec!["push --force*"]);
+        rules.matches.push(MatchRule {
+            id: Some("table".into()),
+            ..match_rule(Action::Deny, "push")
+        });
+        let eval = evaluate(Tool::Git, &rules, &args("push --force"), true);
+        assert_eq!(eval.decision, Decision::Deny);
+        assert_eq!(eval.matched.unwrap().list, RuleList::Deny);
+    }
+
+    #[test]
+    fn test_matched_reports_list_index_and_source() {
+        let mut rules = make_rules(vec![], vec![], vec!["reset --hard*", "push -f*"]);
+        rules.deny[1].source = Some(PathBuf::from("/etc/gg.toml"));
+        let matched = evaluate(Tool::Git, &rules, &args("push origin -f"), true)
+            .matched
+            .unwrap();
+        assert_eq!(matched.list, RuleList::Deny);
+        assert_eq!(matched.index, 1);
+        assert_eq!(matched.pattern, "push -f*");
+        assert_eq!(matched.to_string(), r#"deny[1] "push -f*" in /etc/gg.toml"#);
+
+        let eval = evaluate(Tool::Git, &rules, &args("status"), true);
+        assert_eq!(eval.decision, Decision::DefaultDeny);
+        assert!(eval.matched.is_none());
+    }
+
+    #[test]
+    fn test_evaluate_traced_records_tested_rules() {
+        let rules = make_rules(vec!["push*"], vec!["push origin*"], vec!["push -f*"]);
+        let (eval, trace) = evaluate_traced(Tool::Git, &rules, &[], &args("push origin"), true);
+        assert_eq!(eval.decision, Decision::Confirm);
+        let tested: Vec<(RuleList, bool)> =
+            trace.iter().map(|s| (s.rule.list, s.matched)).collect();
+        assert_eq!(
+            tested,
+            vec![(RuleList::Deny, false), (RuleList::Confirm, true)]
+        );
+    }
 }
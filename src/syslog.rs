@@ -0,0 +1,218 @@
+use crate::config::Options;
+use crate::logger::{Event, Record};
+use crate::rules::Decision;
+use chrono::{Local, SecondsFormat};
+
+#[cfg(target_os = "macos")]
+const DEFAULT_SOCKET: &str = "/var/run/syslog";
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_SOCKET: &str = "/dev/log";
+
+/// Structured-data id, under the private enterprise number reserved for
+/// documentation (RFC 5612)
+const SD_ID: &str = "gg@32473";
+
+const FACILITY_USER: u8 = 1;
+
+/// Send one audit record to the local syslog socket as an RFC 5424 message
+pub fn send(record: &Record, message: &str, options: &Options) {
+    let socket = options
+        .log_syslog_socket
+        .as_deref()
+        .unwrap_or(DEFAULT_SOCKET);
+    let packet = format(record, message, &Local::now(), &hostname());
+    if let Err(e) = deliver(socket, packet.as_bytes()) {
+        eprintln!("[gg] syslog error ({}): {}", socket, e);
+    }
+}
+
+#[cfg(unix)]
+fn deliver(socket: &str, packet: &[u8]) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    let sock = UnixDatagram::unbound()?;
+    // A stuck syslog daemon must not hold up the command
+    sock.set_write_timeout(Some(Duration::from_millis(200)))?;
+    sock.send_to(packet, socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn deliver(_socket: &str, _packet: &[u8]) -> std::io::Result<()> {
+    Err(std::io::Error::other(
+        "the syslog sink needs a Unix syslog socket",
+    ))
+}
+
+/// `<PRI>1 TIMESTAMP HOSTNAME gg PID MSGID [gg@32473 ...] MESSAGE`
+fn format(record: &Record, message: &str, now: &chrono::DateTime<Local>, hostname: &str) -> String {
+    let pri = FACILITY_USER * 8 + severity(record);
+    let mut params = vec![
+        ("tool", record.tool.to_string()),
+        ("decision", record.eval.decision.to_string().to_lowercase()),
+    ];
+    if let Some(id) = record.eval.matched.as_ref().and_then(|m| m.id.clone()) {
+        params.push(("rule", id));
+    }
+    if let Some(code) = record.outcome.and_then(|o| o.exit_code) {
+        params.push(("exit_code", code.to_string()));
+    }
+    let params: Vec<String> = params
+        .into_iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_param(&value)))
+        .collect();
+    format!(
+        "<{}>1 {} {} gg {} {} [{} {}] {}",
+        pri,
+        now.to_rfc3339_opts(SecondsFormat::Millis, false),
+        hostname,
+        std::process::id(),
+        record.event,
+        SD_ID,
+        params.join(" "),
+        message
+    )
+}
+
+/// Blocks are warnings, confirmations and grants notices; everything else is
+/// info
+fn severity(record: &Record) -> u8 {
+    match (record.event, record.eval.decision) {
+        (Event::Decision, Decision::Deny | Decision::DefaultDeny) => 4,
+        (Event::Decision, Decision::Confirm) | (Event::Grant | Event::Revoke, _) => 5,
+        _ => 6,
+    }
+}
+
+/// `"`, `\` and `]` must be escaped inside a PARAM-VALUE
+fn escape_param(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+/// The nil value `-` lets the syslog daemon fill in its own
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty() && h.is_ascii() && !h.contains(' '))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::Tool;
+    use crate::logger::Outcome;
+    use crate::rules::{Evaluation, Matched, RuleList};
+    use chrono::TimeZone;
+
+    fn record<'a>(args: &'a [String], eval: &'a Evaluation) -> Record<'a> {
+        Record {
+            event: Event::Decision,
+            tool: Tool::Git,
+            args,
+            eval,
+            confirmed: None,
+            outcome: None,
+        }
+    }
+
+    fn now() -> chrono::DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_deny_is_a_warning_with_structured_data() {
+        let args = vec!["push".to_string(), "--force".to_string()];
+        let eval = Evaluation {
+            decision: Decision::Deny,
+            matched: Some(Matched {
+                list: RuleList::Table,
+                index: 0,
+                pattern: "push --force*".to_string(),
+                source: None,
+                id: Some("no-\"force\"]".to_string()),
+                reason: None,
+                severity: None,
+                suggest: None,
+            }),
+        };
+        let packet = format(
+            &record(&args, &eval),
+            "DENY | git push --force",
+            &now(),
+            "box",
+        );
+        let prefix = format!(
+            "<12>1 {} box gg {} decision ",
+            now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            std::process::id()
+        );
+        assert!(packet.starts_with(&prefix), "{}", packet);
+        assert!(packet.ends_with(
+            r#"[gg@32473 tool="git" decision="deny" rule="no-\"force\"\]"] DENY | git push --force"#
+        ));
+    }
+
+    #[test]
+    fn test_severity_and_result_fields() {
+        let args = vec!["status".to_string()];
+        let eval = Evaluation {
+            decision: Decision::Allow,
+            matched: None,
+        };
+        let result = Record {
+            event: Event::Result,
+            outcome: Some(Outcome {
+                exit_code: Some(3),
+                ..Outcome::default()
+            }),
+            ..record(&args, &eval)
+        };
+        let packet = format(&result, "RESULT | git status", &now(), "-");
+        assert!(packet.starts_with("<14>1 "));
+        assert!(packet.contains(" - gg "));
+        assert!(packet.contains(r#"decision="allow" exit_code="3"]"#));
+
+        let confirm = Evaluation {
+            decision: Decision::Confirm,
+            matched: None,
+        };
+        assert_eq!(severity(&record(&args, &confirm)), 5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_to_socket() {
+        use std::os::unix::net::UnixDatagram;
+
+        let dir = std::env::temp_dir().join("gg_test_syslog_send");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+
+        let args = vec!["status".to_string()];
+        let eval = Evaluation {
+            decision: Decision::Allow,
+            matched: None,
+        };
+        let options = Options {
+            log_syslog_socket: Some(path.to_str().unwrap().to_string()),
+            ..Options::default()
+        };
+        send(&record(&args, &eval), "ALLOW | git status", &options);
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let packet = String::from_utf8_lossy(&buf[..n]);
+        assert!(packet.starts_with("<14>1 "), "{}", packet);
+        assert!(packet.ends_with("] ALLOW | git status"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
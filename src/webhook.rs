@@ -0,0 +1,168 @@
+use crate::config::Options;
+use crate::detect::Tool;
+use crate::logger::Context;
+use crate::redact;
+use crate::rules::{Evaluation, Matched};
+use chrono::{Local, SecondsFormat};
+use serde_json::json;
+use std::time::Duration;
+
+/// POST the decision to `webhook_url` if it is one of `webhook_on`. Failures
+/// are reported and otherwise ignored.
+pub fn notify(options: &Options, tool: Tool, args: &[String], eval: &Evaluation) {
+    let Some(url) = &options.webhook_url else {
+        return;
+    };
+    if !options.webhook_on.contains(&eval.decision) {
+        return;
+    }
+    let args = redact::args(args, &options.log_redact);
+    let body = payload(tool, &args, eval, Context::current());
+    let timeout = Duration::from_millis(options.webhook_timeout_ms);
+    if let Err(e) = post(url, &body, timeout) {
+        eprintln!("[gg] webhook error: {}", e);
+    }
+}
+
+/// A JSON object whose `text` reads well as a Slack-style message; the other
+/// fields are for anything that wants to route on them
+fn payload(tool: Tool, args: &[String], eval: &Evaluation, context: &Context) -> String {
+    let mut text = format!("[gg] {}: `{} {}`", eval.decision, tool, args.join(" "));
+    if let Some(root) = &context.repo_root {
+        text.push_str(&format!(" in {}", root));
+    }
+    if let Some(matched) = &eval.matched {
+        match &matched.reason {
+            Some(reason) => text.push_str(&format!(" — {}", reason)),
+            None => text.push_str(&format!(" — rule {}", matched)),
+        }
+    }
+    json!({
+        "text": text,
+        "ts": Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        "tool": tool.to_string(),
+        "args": args,
+        "decision": eval.decision.to_string().to_lowercase(),
+        "rule": eval.matched.as_ref().map(Matched::to_json),
+        "reason": eval.matched.as_ref().and_then(|m| m.reason.clone()),
+        "severity": eval.matched.as_ref().and_then(|m| m.severity).map(|s| s.to_string()),
+        "cwd": context.cwd,
+        "repo_root": context.repo_root,
+        "remote_url": context.remote_url,
+        "parent": context.parent,
+    })
+    .to_string()
+}
+
+fn post(url: &str, body: &str, timeout: Duration) -> Result<(), String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout(timeout)
+        .build();
+    agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(body)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Decision, RuleList};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+    use std::time::Instant;
+
+    /// One-shot HTTP server on a free port. Returns its URL and the body it
+    /// received; with `respond = false` it reads the request and then hangs.
+    fn stub(respond: bool) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            if respond {
+                reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .unwrap();
+            } else {
+                std::thread::sleep(Duration::from_secs(2));
+            }
+            String::from_utf8(body).unwrap()
+        });
+        (url, handle)
+    }
+
+    fn deny() -> Evaluation {
+        Evaluation {
+            decision: Decision::Deny,
+            matched: Some(Matched {
+                list: RuleList::Deny,
+                index: 0,
+                pattern: "repo delete*".to_string(),
+                source: None,
+                id: None,
+                reason: None,
+                severity: None,
+                suggest: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_payload() {
+        let args = vec!["repo".to_string(), "delete".to_string(), "foo".to_string()];
+        let context = Context {
+            repo_root: Some("/src/app".to_string()),
+            ..Context::default()
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(&payload(Tool::Gh, &args, &deny(), &context)).unwrap();
+        assert_eq!(
+            value["text"],
+            "[gg] DENY: `gh repo delete foo` in /src/app — rule deny[0] \"repo delete*\""
+        );
+        assert_eq!(value["decision"], "deny");
+        assert_eq!(value["rule"]["pattern"], "repo delete*");
+        assert_eq!(value["args"], json!(["repo", "delete", "foo"]));
+    }
+
+    #[test]
+    fn test_notify_posts_to_endpoint() {
+        let (url, server) = stub(true);
+        let options = Options {
+            webhook_url: Some(url),
+            ..Options::default()
+        };
+        let args = vec!["repo".to_string(), "delete".to_string()];
+        notify(&options, Tool::Gh, &args, &deny());
+        let body = server.join().unwrap();
+        assert!(body.contains(r#""decision":"deny""#), "{}", body);
+    }
+
+    #[test]
+    fn test_dead_endpoint_times_out() {
+        let (url, _server) = stub(false);
+        let start = Instant::now();
+        let result = post(&url, "{}", Duration::from_millis(200));
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}